@@ -32,9 +32,9 @@ async fn main() -> eyre::Result<()> {
     abigen!(
         CrowdFunding,
         r#"[
-            function create_campaign(address owner, string memory title, string memory description, uint256 target, uint256 deadline, string memory image) external returns (uint256)
-            function donate_to_campaign(uint256 campaign_id) external payable
-            function get_campaigns() external view returns (address[] memory, string[] memory, string[] memory, uint256[] memory, uint256[] memory, string[] memory, address[][] memory, uint256[][] memory)
+            function create_campaign(address owner, string memory title, string memory description, uint256 target, uint256 deadline, string memory image, uint8 funding_model, bool retractable, address token, uint256 min_donation, uint256 max_donation, uint256 max_per_donor, uint256 hard_cap, bytes32 allowlist_root, uint256[] memory milestone_amounts, bytes32[] memory milestone_hashes) external returns (uint256)
+            function donate_to_campaign(uint256 campaign_id, bytes32[] memory proof) external payable
+            function get_campaigns() external view returns (address[] memory, string[] memory, string[] memory, uint256[] memory, uint256[] memory, string[] memory, address[][] memory, uint256[][] memory, uint8[] memory, address[] memory, uint256[] memory, uint256[] memory)
         ]"#
     );
 
@@ -58,9 +58,31 @@ async fn main() -> eyre::Result<()> {
     let target = U256::from(1000000000000000000u64); // 1 ETH
     let deadline = U256::from(1680000000u64); // Set an appropriate deadline timestamp
     let image = "https://example.com/image.jpg".to_string();
+    let funding_model = 1u8; // All-or-nothing
 
-    let tx = crowdfunding.create_campaign(owner, title, description, target, deadline, image);
-    let receipt = tx.send().await?.await?;
+    let tx = crowdfunding.create_campaign(
+        owner,
+        title,
+        description,
+        target,
+        deadline,
+        image,
+        funding_model,
+        false,
+        Address::zero(), // Denominated in ETH
+        U256::zero(),    // No minimum donation
+        U256::zero(),    // No maximum donation
+        U256::zero(),    // No per-donor cap
+        U256::zero(),    // No hard cap
+        [0u8; 32],       // Public campaign, no allowlist
+        vec![],
+        vec![],
+    );
+    let receipt = tx
+        .send()
+        .await?
+        .await?
+        .ok_or_else(|| eyre!("Campaign creation was dropped from the mempool"))?;
     println!("Campaign created. Receipt: {:?}", receipt);
 
     // Get the campaign ID from the transaction logs (assuming it's the first topic)
@@ -75,7 +97,7 @@ async fn main() -> eyre::Result<()> {
 
     // Donate to the campaign
     let donation_amount = U256::from(100000000000000000u64); // 0.1 ETH
-    let tx = crowdfunding.donate_to_campaign(campaign_id);
+    let tx = crowdfunding.donate_to_campaign(campaign_id, vec![]);
     let receipt = tx.value(donation_amount).send().await?.await?;
     println!("Donation made. Receipt: {:?}", receipt);
 
@@ -91,6 +113,6 @@ fn read_secret_from_file(fpath: &str) -> eyre::Result<String> {
     let mut buf_reader = BufReader::new(f);
     let mut secret = String::new();
     buf_reader.read_line(&mut secret)?;
-    println!("Secret read from file", secret);
+    println!("Secret read from file");
     Ok(secret.trim().to_string())
 }
\ No newline at end of file
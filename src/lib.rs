@@ -1,5 +1,5 @@
 // Allow `cargo stylus export-abi` to generate a main function.
-#![cfg_attr(not(feature = "export-abi"), no_main)]
+#![cfg_attr(not(any(test, feature = "export-abi")), no_main)]
 
 // Set up a global memory allocator using MiniAlloc for efficient memory management in the smart contract.
 #[global_allocator]
@@ -7,17 +7,39 @@ static ALLOC: mini_alloc::MiniAlloc = mini_alloc::MiniAlloc::INIT;
 
 extern crate alloc;
 
-use stylus_sdk::{alloy_primitives::U256, prelude::*, evm, alloy_sol_types::sol};
-use stylus_sdk::call::transfer_eth;
-use alloy_primitives::{Address, Uint};
-use stylus_sdk::{block, console, msg};
+use stylus_sdk::{alloy_primitives::U256, prelude::*, crypto, evm, alloy_sol_types::{sol, SolCall, SolError, SolValue}};
+use stylus_sdk::call::{self, transfer_eth, Call};
+use alloy_primitives::{fixed_bytes, Address, FixedBytes, U8};
+use stylus_sdk::{block, contract, msg};
 use stylus_sdk::storage::{StorageAddress, StorageString, StorageU256, StorageVec};
 
+// The storage layout is shared with every deployment behind a proxy, so fields are only ever
+// appended: never reorder, retype or remove one without bumping `STORAGE_VERSION`. Slots are
+// assigned in declaration order and packed like Solidity's, which leaves `CrowdFunding` at:
+//
+//   0: no_of_campaigns      3: treasury     6: paused            9: campaigns_by_donor
+//   1: campaigns            4: fee_bps      7: storage_version
+//   2: locked, admin        5: roles        8: campaigns_by_owner
+//
+// `CampaignStorage` follows the same rule: the original fields `owner` through `donations` keep
+// their slots and every later field sits after them in the order it was added.
+//
+// ERC-1967 proxies keep their implementation and admin addresses in hashed slots that cannot
+// collide with these.
 sol_storage! {
     #[entrypoint]
     pub struct CrowdFunding {
         uint256 no_of_campaigns;
         mapping(uint256 => CampaignStorage) campaigns;
+        bool locked;
+        address admin;
+        address treasury;
+        uint256 fee_bps;
+        mapping(bytes32 => mapping(address => bool)) roles;
+        bool paused;
+        uint256 storage_version;
+        mapping(address => uint256[]) campaigns_by_owner;
+        mapping(address => uint256[]) campaigns_by_donor;
     }
 
     struct CampaignStorage {
@@ -30,96 +52,1384 @@ sol_storage! {
         StorageString image;
         StorageVec<StorageAddress> donators;
         StorageVec<StorageU256> donations;
+        uint8 funding_model;
+        uint256 amount_withdrawn;
+        mapping(address => bool) refunded;
+        StorageVec<Milestone> milestones;
+        uint256 current_milestone;
+        bool milestone_failed;
+        bool cancelled;
+        uint256 original_deadline;
+        uint256 revision;
+        mapping(address => uint256) contributions;
+        uint256 donor_count;
+        bool retractable;
+        StorageAddress token;
+        uint256 min_donation;
+        uint256 max_donation;
+        uint256 max_per_donor;
+        uint256 hard_cap;
+        bytes32 allowlist_root;
+        uint8 status;
+        mapping(address => bool) indexed_donors;
+        uint256 fees_charged;
+        uint256 payout_count;
+        mapping(address => uint256) held_contributions;
+        mapping(address => uint256) held_since_payout;
     }
+
+    struct Milestone {
+        uint256 amount;
+        bytes32 description_hash;
+        uint256 voting_deadline;
+        uint256 approvals;
+        uint256 rejections;
+        mapping(address => bool) voted;
+    }
+}
+
+/// Version of the storage layout written by `initialize`.
+const STORAGE_VERSION: u64 = 1;
+
+/// How long donors have to vote on a proposed milestone, in seconds.
+const MILESTONE_VOTING_PERIOD: u64 = 7 * 24 * 60 * 60;
+
+/// How long the owner has to propose the next milestone, in seconds, counted from the campaign
+/// deadline for the first one and from the end of the previous vote for the others.
+const MILESTONE_PROPOSAL_PERIOD: u64 = 30 * 24 * 60 * 60;
+
+/// How far past its original deadline a campaign can be extended, in seconds.
+const MAX_DEADLINE_EXTENSION: u64 = 30 * 24 * 60 * 60;
+
+/// `keccak256("ADMIN_ROLE")`: grants and revokes every role.
+pub const ADMIN_ROLE: FixedBytes<32> =
+    fixed_bytes!("a49807205ce4d355092ef5a8a18f56e8913cf4a201fbe287825b095693c21775");
+
+/// `keccak256("PAUSER_ROLE")`: pauses and unpauses the contract.
+pub const PAUSER_ROLE: FixedBytes<32> =
+    fixed_bytes!("65d7a28e3265b37a6474929f336521b332c1681b933f6cb9f3376673440d862a");
+
+/// `keccak256("VERIFIER_ROLE")`: reserved for campaign moderation.
+pub const VERIFIER_ROLE: FixedBytes<32> =
+    fixed_bytes!("0ce23c3e399818cfee81a7ab0880f714e53d7672b08df0fa62f2843416e1ea09");
+
+/// `keccak256("TREASURER_ROLE")`: configures the platform fee and treasury.
+pub const TREASURER_ROLE: FixedBytes<32> =
+    fixed_bytes!("3496e2e73c4d42b75d702e60d9e48102720b8691234415963a5a857b86425d07");
+
+/// Upper bound of the platform fee, in basis points (10%).
+const MAX_FEE_BPS: u64 = 1_000;
+
+/// Basis points in 100%.
+const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Most entries a paginated view returns in one call; larger limits are clamped to it.
+const MAX_PAGE_SIZE: u64 = 100;
+
+// `sol_interface!` drops attributes placed on an interface, so the lint for `permit`'s nine
+// generated arguments is allowed on a module around it instead.
+#[allow(clippy::too_many_arguments)]
+mod erc20 {
+    use stylus_sdk::{alloy_sol_types::sol, prelude::*};
+
+    sol_interface! {
+        interface IERC20 {
+            function allowance(address owner, address spender) external view returns (uint256);
+        }
+
+        interface IERC20Permit {
+            function permit(address owner, address spender, uint256 value, uint256 deadline, uint8 v, bytes32 r, bytes32 s) external;
+            function DOMAIN_SEPARATOR() external view returns (bytes32);
+        }
+    }
+
+    // Calls generated by `sol_interface!` require the `bool` that tokens such as USDT never
+    // return, so transfers are encoded here and sent with `CrowdFunding::call_token`.
+    sol! {
+        function transfer(address to, uint256 amount) external returns (bool);
+        function transferFrom(address from, address to, uint256 amount) external returns (bool);
+    }
+}
+
+use erc20::{transferCall, transferFromCall, IERC20, IERC20Permit};
+
+sol! {
+    event CampaignCreated(uint256 indexed campaignId, address owner, string title, uint256 target, uint256 deadline, uint8 fundingModel, address token);
+    event DonationMade(uint256 indexed campaignId, address donor, uint256 amount);
+    event Withdrawn(uint256 indexed campaignId, address owner, uint256 amount, uint256 fee);
+    event RefundClaimed(uint256 indexed campaignId, address donor, uint256 amount);
+    event MilestoneProposed(uint256 indexed campaignId, uint256 milestoneIndex, uint256 amount, bytes32 descriptionHash, uint256 votingDeadline);
+    event MilestoneVoted(uint256 indexed campaignId, uint256 milestoneIndex, address voter, bool approve, uint256 weight);
+    event MilestoneReleased(uint256 indexed campaignId, uint256 milestoneIndex, uint256 amount, uint256 fee);
+    event MilestoneRejected(uint256 indexed campaignId, uint256 milestoneIndex, uint256 approvals, uint256 rejections);
+    event MilestoneMissed(uint256 indexed campaignId, uint256 milestoneIndex, uint256 proposalDeadline);
+    event CampaignStatusChanged(uint256 indexed campaignId, uint8 previousStatus, uint8 newStatus);
+    event CampaignCancelled(uint256 indexed campaignId, address owner);
+    event DeadlineExtended(uint256 indexed campaignId, uint256 oldDeadline, uint256 newDeadline);
+    event AllowlistRootUpdated(uint256 indexed campaignId, bytes32 oldRoot, bytes32 newRoot);
+    event HardCapReached(uint256 indexed campaignId, uint256 amountCollected);
+    event CampaignClosedEarly(uint256 indexed campaignId, uint256 deadline, uint256 amountCollected);
+    event DonationWithdrawn(uint256 indexed campaignId, address donor, uint256 amount);
+    event Initialized(address admin, uint256 storageVersion);
+    event RoleGranted(bytes32 indexed role, address indexed account, address indexed sender);
+    event RoleRevoked(bytes32 indexed role, address indexed account, address indexed sender);
+    event Paused(address account);
+    event Unpaused(address account);
+    event FeeUpdated(uint256 oldFeeBps, uint256 newFeeBps);
+    event TreasuryUpdated(address oldTreasury, address newTreasury);
+    event CampaignUpdated(uint256 indexed campaignId, uint256 revision, bytes32 oldContentHash, bytes32 newContentHash);
+
+    error DeadlineNotInFuture(uint256 deadline, uint256 currentTime);
+    error CampaignNotFound(uint256 campaignId);
+    error DeadlinePassed(uint256 deadline, uint256 currentTime);
+    error ZeroDonation();
+    error CampaignStillActive(uint256 deadline, uint256 currentTime);
+    error TargetNotReached(uint256 amountCollected, uint256 target);
+    error TargetReached(uint256 amountCollected, uint256 target);
+    error NotCampaignOwner(uint256 campaignId, address caller);
+    error NothingToWithdraw(uint256 campaignId);
+    error InvalidFundingModel(uint8 fundingModel);
+    error InvalidCampaignStatus(uint8 status);
+    error InvalidStatusTransition(uint8 previousStatus, uint8 newStatus);
+    error NotRefundable(uint256 campaignId);
+    error AlreadyRefunded(uint256 campaignId, address donor);
+    error NothingToRefund(uint256 campaignId, address donor);
+    error MilestoneCountMismatch(uint256 amounts, uint256 descriptionHashes);
+    error MilestonesRequireEscrow();
+    error ZeroMilestoneAmount(uint256 milestoneIndex);
+    error MilestoneTotalMismatch(uint256 total, uint256 target);
+    error NoMilestones(uint256 campaignId);
+    error MilestonesPending(uint256 campaignId);
+    error MilestoneVoteFailed(uint256 campaignId);
+    error AllMilestonesReleased(uint256 campaignId);
+    error MilestoneAlreadyProposed(uint256 campaignId, uint256 milestoneIndex);
+    error MilestoneNotProposed(uint256 campaignId, uint256 milestoneIndex);
+    error VotingClosed(uint256 votingDeadline, uint256 currentTime);
+    error VotingStillOpen(uint256 votingDeadline, uint256 currentTime);
+    error ProposalPeriodOver(uint256 proposalDeadline, uint256 currentTime);
+    error ProposalPeriodOpen(uint256 proposalDeadline, uint256 currentTime);
+    error NotADonor(uint256 campaignId, address caller);
+    error AlreadyVoted(uint256 campaignId, uint256 milestoneIndex, address voter);
+    error CampaignIsCancelled(uint256 campaignId);
+    error RetractionRequiresEscrow();
+    error RetractionNotAllowed(uint256 campaignId);
+    error ZeroRetraction();
+    error RetractionExceedsContribution(uint256 contribution, uint256 amount);
+    error ReentrantCall();
+    error WrongDenomination(address expected, address provided);
+    error TokenTransferFailed(address token);
+    error PermitNotSupported(address token);
+    error PermitFailed(address token);
+    error AlreadyInitialized(uint256 storageVersion);
+    error InvalidAdmin(address admin);
+    error MissingRole(bytes32 role, address account);
+    error ContractPaused();
+    error NotAllowlisted(uint256 campaignId, address donor);
+    error InvalidHardCap(uint256 hardCap, uint256 target);
+    error InvalidDonationLimits(uint256 minDonation, uint256 maxDonation);
+    error DonationBelowMinimum(uint256 amount, uint256 minDonation);
+    error DonationAboveMaximum(uint256 amount, uint256 maxDonation);
+    error DonorCapExceeded(uint256 contribution, uint256 maxPerDonor);
+    error ContractNotPaused();
+    error InvalidTreasury(address treasury);
+    error TreasuryNotSet();
+    error FeeTooHigh(uint256 feeBps, uint256 maxFeeBps);
+    error DeadlineNotExtended(uint256 currentDeadline, uint256 newDeadline);
+    error DeadlineExtensionTooLong(uint256 newDeadline, uint256 maxDeadline);
+}
+
+/// How the donations of a campaign are released to its owner.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FundingModel {
+    /// The owner keeps whatever is raised and can withdraw it at any time.
+    KeepItAll = 0,
+    /// Donations are escrowed until the deadline and refunded if the target is missed.
+    AllOrNothing = 1,
+}
+
+impl TryFrom<u8> for FundingModel {
+    type Error = Vec<u8>;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(FundingModel::KeepItAll),
+            1 => Ok(FundingModel::AllOrNothing),
+            _ => Err(InvalidFundingModel { fundingModel: value }.abi_encode()),
+        }
+    }
+}
+
+/// Lifecycle of a campaign. `Active` campaigns whose deadline has passed are reported as
+/// `Successful` or `Failed` straight away, and that outcome is written to storage by the next
+/// transaction touching the campaign.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CampaignStatus {
+    /// Not launched yet; `create_campaign` moves new campaigns straight to `Active`.
+    Draft = 0,
+    /// Accepting donations until the deadline.
+    Active = 1,
+    /// Ended with its target met, or with any amount for keep-it-all campaigns.
+    Successful = 2,
+    /// Ended below its target, or a milestone was rejected. Remaining funds are refundable.
+    Failed = 3,
+    /// Stopped by its owner. Remaining funds are refundable.
+    Cancelled = 4,
+    /// Everything raised has been paid out to the owner.
+    Withdrawn = 5,
 }
 
-sol! {
-    event CampaignCreated(uint256 indexed campaignId, address owner, string title, uint256 target, uint256 deadline);
-    event DonationMade(uint256 indexed campaignId, address donor, uint256 amount);
-}
+impl CampaignStatus {
+    fn can_become(self, next: CampaignStatus) -> bool {
+        use CampaignStatus::*;
+        matches!(
+            (self, next),
+            (Draft, Active)
+                | (Active, Successful)
+                | (Active, Failed)
+                | (Active, Cancelled)
+                | (Successful, Withdrawn)
+                | (Successful, Failed)
+                | (Successful, Cancelled)
+                | (Failed, Cancelled)
+        )
+    }
+}
+
+impl TryFrom<u8> for CampaignStatus {
+    type Error = Vec<u8>;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(CampaignStatus::Draft),
+            1 => Ok(CampaignStatus::Active),
+            2 => Ok(CampaignStatus::Successful),
+            3 => Ok(CampaignStatus::Failed),
+            4 => Ok(CampaignStatus::Cancelled),
+            5 => Ok(CampaignStatus::Withdrawn),
+            _ => Err(InvalidCampaignStatus { status: value }.abi_encode()),
+        }
+    }
+}
+
+/// Milestones of a campaign as parallel arrays: amounts, description hashes, voting deadlines,
+/// approvals and rejections.
+pub type MilestonesView = (Vec<U256>, Vec<FixedBytes<32>>, Vec<U256>, Vec<U256>, Vec<U256>);
+
+/// Every campaign as parallel arrays: owners, titles, descriptions, targets, deadlines, images,
+/// donators, donations, funding models, tokens, gross amounts and net amounts.
+pub type CampaignsView = (
+    Vec<Address>,
+    Vec<String>,
+    Vec<String>,
+    Vec<U256>,
+    Vec<U256>,
+    Vec<String>,
+    Vec<Vec<Address>>,
+    Vec<Vec<U256>>,
+    Vec<u8>,
+    Vec<Address>,
+    Vec<U256>,
+    Vec<U256>,
+);
+
+/// A page of campaigns as parallel arrays: owners, titles, descriptions, targets, deadlines,
+/// images, funding models, tokens, gross amounts and net amounts, then the total campaign count.
+pub type CampaignsPage = (
+    Vec<Address>,
+    Vec<String>,
+    Vec<String>,
+    Vec<U256>,
+    Vec<U256>,
+    Vec<String>,
+    Vec<u8>,
+    Vec<Address>,
+    Vec<U256>,
+    Vec<U256>,
+    U256,
+);
+
+/// One campaign: owner, title, description, target, deadline, amount collected, image, donor
+/// count and status.
+pub type CampaignView = (Address, String, String, U256, U256, U256, String, U256, u8);
+
+#[external]
+impl CrowdFunding {
+    /// Sets up a fresh deployment. Behind an ERC-1967 proxy this should be passed as the proxy's
+    /// initialization call so that nobody can initialize it first.
+    pub fn initialize(&mut self, admin: Address) -> Result<(), Vec<u8>> {
+        let storage_version = self.storage_version.get();
+        if storage_version != U256::from(0) {
+            return Err(AlreadyInitialized { storageVersion: storage_version }.abi_encode());
+        }
+
+        if admin == Address::default() {
+            return Err(InvalidAdmin { admin }.abi_encode());
+        }
+
+        self.storage_version.set(U256::from(STORAGE_VERSION));
+        self.admin.set(admin);
+
+        // Emit Initialized event
+        evm::log(Initialized {
+            admin,
+            storageVersion: U256::from(STORAGE_VERSION),
+        });
+
+        for role in [ADMIN_ROLE, PAUSER_ROLE, VERIFIER_ROLE, TREASURER_ROLE] {
+            self.set_role(role, admin, true);
+        }
+
+        Ok(())
+    }
+
+    #[view]
+    pub fn get_storage_version(&self) -> U256 {
+        self.storage_version.get()
+    }
+
+    #[view]
+    pub fn has_role(&self, role: FixedBytes<32>, account: Address) -> bool {
+        self.roles.get(role).get(account)
+    }
+
+    pub fn grant_role(&mut self, role: FixedBytes<32>, account: Address) -> Result<(), Vec<u8>> {
+        self.only_role(ADMIN_ROLE)?;
+        self.set_role(role, account, true);
+        Ok(())
+    }
+
+    pub fn revoke_role(&mut self, role: FixedBytes<32>, account: Address) -> Result<(), Vec<u8>> {
+        self.only_role(ADMIN_ROLE)?;
+        self.set_role(role, account, false);
+        Ok(())
+    }
+
+    pub fn renounce_role(&mut self, role: FixedBytes<32>) -> Result<(), Vec<u8>> {
+        self.only_role(role)?;
+        self.set_role(role, msg::sender(), false);
+        Ok(())
+    }
+
+    pub fn set_treasury(&mut self, treasury: Address) -> Result<(), Vec<u8>> {
+        self.only_role(TREASURER_ROLE)?;
+
+        if treasury == Address::default() {
+            return Err(InvalidTreasury { treasury }.abi_encode());
+        }
+
+        let old_treasury = self.treasury.get();
+        self.treasury.set(treasury);
+
+        // Emit TreasuryUpdated event
+        evm::log(TreasuryUpdated {
+            oldTreasury: old_treasury,
+            newTreasury: treasury,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_fee(&mut self, fee_bps: U256) -> Result<(), Vec<u8>> {
+        self.only_role(TREASURER_ROLE)?;
+
+        if fee_bps > U256::from(MAX_FEE_BPS) {
+            return Err(FeeTooHigh { feeBps: fee_bps, maxFeeBps: U256::from(MAX_FEE_BPS) }.abi_encode());
+        }
+
+        if fee_bps > U256::from(0) && self.treasury.get() == Address::default() {
+            return Err(TreasuryNotSet {}.abi_encode());
+        }
+
+        let old_fee_bps = self.fee_bps.get();
+        self.fee_bps.set(fee_bps);
+
+        // Emit FeeUpdated event
+        evm::log(FeeUpdated {
+            oldFeeBps: old_fee_bps,
+            newFeeBps: fee_bps,
+        });
+
+        Ok(())
+    }
+
+    pub fn pause(&mut self) -> Result<(), Vec<u8>> {
+        self.only_role(PAUSER_ROLE)?;
+        self.when_not_paused()?;
+
+        self.paused.set(true);
+
+        // Emit Paused event
+        evm::log(Paused { account: msg::sender() });
+
+        Ok(())
+    }
+
+    pub fn unpause(&mut self) -> Result<(), Vec<u8>> {
+        self.only_role(PAUSER_ROLE)?;
+        if !self.paused.get() {
+            return Err(ContractNotPaused {}.abi_encode());
+        }
+
+        self.paused.set(false);
+
+        // Emit Unpaused event
+        evm::log(Unpaused { account: msg::sender() });
+
+        Ok(())
+    }
+
+    #[view]
+    pub fn is_paused(&self) -> bool {
+        self.paused.get()
+    }
+
+    #[view]
+    pub fn get_fee_config(&self) -> (Address, U256) {
+        (self.treasury.get(), self.fee_bps.get())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_campaign(
+        &mut self,
+        owner: Address,
+        title: String,
+        description: String,
+        target: U256,
+        deadline: U256,
+        image: String,
+        funding_model: u8,
+        retractable: bool,
+        token: Address,
+        min_donation: U256,
+        max_donation: U256,
+        max_per_donor: U256,
+        hard_cap: U256,
+        allowlist_root: FixedBytes<32>,
+        milestone_amounts: Vec<U256>,
+        milestone_hashes: Vec<FixedBytes<32>>,
+    ) -> Result<U256, Vec<u8>> {
+        self.when_not_paused()?;
+
+        let number_of_campaigns = self.no_of_campaigns.get();
+
+        let model = FundingModel::try_from(funding_model)?;
+
+        // Donors can only take back pledges that are still held in escrow.
+        if retractable && model != FundingModel::AllOrNothing {
+            return Err(RetractionRequiresEscrow {}.abi_encode());
+        }
+
+        // A zero hard cap or maximum means the campaign has no such limit.
+        if hard_cap != U256::from(0) && hard_cap < target {
+            return Err(InvalidHardCap { hardCap: hard_cap, target }.abi_encode());
+        }
+
+        if max_donation != U256::from(0) && min_donation > max_donation {
+            return Err(InvalidDonationLimits { minDonation: min_donation, maxDonation: max_donation }.abi_encode());
+        }
+
+        if milestone_amounts.len() != milestone_hashes.len() {
+            return Err(MilestoneCountMismatch {
+                amounts: U256::from(milestone_amounts.len()),
+                descriptionHashes: U256::from(milestone_hashes.len()),
+            }
+            .abi_encode());
+        }
+
+        if !milestone_amounts.is_empty() {
+            // Tranches are paid out of escrow, so only all-or-nothing campaigns can use them.
+            if model != FundingModel::AllOrNothing {
+                return Err(MilestonesRequireEscrow {}.abi_encode());
+            }
+
+            let mut total = U256::from(0);
+            for (i, amount) in milestone_amounts.iter().enumerate() {
+                if *amount == U256::from(0) {
+                    return Err(ZeroMilestoneAmount { milestoneIndex: U256::from(i) }.abi_encode());
+                }
+                total += *amount;
+            }
+            if total != target {
+                return Err(MilestoneTotalMismatch { total, target }.abi_encode());
+            }
+        }
+
+        let current_time = U256::from(block::timestamp());
+        if deadline <= current_time {
+            return Err(DeadlineNotInFuture { deadline, currentTime: current_time }.abi_encode());
+        }
+
+        let mut campaign_accessor = self.campaigns.setter(number_of_campaigns);
+
+        campaign_accessor.owner.set(owner);
+        campaign_accessor.title.set_str(&title);
+        campaign_accessor.description.set_str(&description);
+        campaign_accessor.target.set(target);
+        campaign_accessor.deadline.set(deadline);
+        campaign_accessor.original_deadline.set(deadline);
+        campaign_accessor.amount_collected.set(U256::from(0));
+        campaign_accessor.image.set_str(&image);
+        campaign_accessor.funding_model.set(U8::from(funding_model));
+        campaign_accessor.retractable.set(retractable);
+        // The target and every amount of the campaign are denominated in `token`,
+        // or in ETH when it is the zero address.
+        campaign_accessor.token.set(token);
+        campaign_accessor.min_donation.set(min_donation);
+        campaign_accessor.max_donation.set(max_donation);
+        campaign_accessor.max_per_donor.set(max_per_donor);
+        campaign_accessor.hard_cap.set(hard_cap);
+        // A zero root makes the campaign public.
+        campaign_accessor.allowlist_root.set(allowlist_root);
+        // StorageVec is automatically initialized, no need to call initialize()
+        for (amount, description_hash) in milestone_amounts.into_iter().zip(milestone_hashes) {
+            let mut milestone = campaign_accessor.milestones.grow();
+            milestone.amount.set(amount);
+            milestone.description_hash.set(description_hash);
+        }
+        Self::set_status(&mut campaign_accessor, number_of_campaigns, CampaignStatus::Active)?;
+
+        self.campaigns_by_owner.setter(owner).push(number_of_campaigns);
+        self.no_of_campaigns.set(number_of_campaigns + U256::from(1));
+
+        // Emit CampaignCreated event
+        evm::log(CampaignCreated {
+            campaignId: number_of_campaigns,
+            owner,
+            title,
+            target,
+            deadline,
+            fundingModel: funding_model,
+            token,
+        });
+
+        Ok(number_of_campaigns)
+    }
+
+    #[payable]
+    pub fn donate_to_campaign(&mut self, campaign_id: U256, proof: Vec<FixedBytes<32>>) -> Result<(), Vec<u8>> {
+        self.lock()?;
+
+        let accepted = self.record_donation(campaign_id, Address::default(), msg::value(), &proof)?;
+
+        let surplus = msg::value() - accepted;
+        if surplus > U256::from(0) {
+            self.pay_out(Address::default(), msg::sender(), surplus)?;
+        }
+
+        self.unlock();
+        Ok(())
+    }
+
+    pub fn donate_token(
+        &mut self,
+        campaign_id: U256,
+        token: Address,
+        amount: U256,
+        proof: Vec<FixedBytes<32>>,
+    ) -> Result<(), Vec<u8>> {
+        self.lock()?;
+
+        if token == Address::default() {
+            let expected = self.campaigns.get(campaign_id).token.get();
+            return Err(WrongDenomination { expected, provided: token }.abi_encode());
+        }
+
+        let accepted = self.record_donation(campaign_id, token, amount, &proof)?;
+        self.pull_tokens(token, msg::sender(), accepted)?;
+
+        self.unlock();
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn donate_token_with_permit(
+        &mut self,
+        campaign_id: U256,
+        token: Address,
+        amount: U256,
+        permit_deadline: U256,
+        v: u8,
+        r: FixedBytes<32>,
+        s: FixedBytes<32>,
+        proof: Vec<FixedBytes<32>>,
+    ) -> Result<(), Vec<u8>> {
+        self.lock()?;
+
+        if token == Address::default() {
+            let expected = self.campaigns.get(campaign_id).token.get();
+            return Err(WrongDenomination { expected, provided: token }.abi_encode());
+        }
+
+        let donor = msg::sender();
+        let spender = contract::address();
+        let permitted = IERC20Permit::new(token)
+            .permit(Call::new_in(self), donor, spender, amount, permit_deadline, v, r, s)
+            .is_ok();
+        if !permitted {
+            // The permit may have been front-run with the same signature, in which case the
+            // allowance is already in place and the donation can go ahead.
+            let allowance = IERC20::new(token)
+                .allowance(Call::new_in(self), donor, spender)
+                .unwrap_or_default();
+            if allowance < amount {
+                // EIP-2612 tokens expose a domain separator, which tells a rejected permit (expired
+                // or badly signed) apart from a token that has no permit at all.
+                if IERC20Permit::new(token).domain_separator(Call::new_in(self)).is_ok() {
+                    return Err(PermitFailed { token }.abi_encode());
+                }
+                return Err(PermitNotSupported { token }.abi_encode());
+            }
+        }
+
+        let accepted = self.record_donation(campaign_id, token, amount, &proof)?;
+        self.pull_tokens(token, donor, accepted)?;
+
+        self.unlock();
+        Ok(())
+    }
+
+    pub fn retract_donation(&mut self, campaign_id: U256, amount: U256) -> Result<(), Vec<u8>> {
+        self.lock()?;
+
+        let donor = msg::sender();
+        let mut campaign_accessor = self.campaigns.setter(campaign_id);
+
+        if campaign_accessor.owner.get() == Address::default() {
+            return Err(CampaignNotFound { campaignId: campaign_id }.abi_encode());
+        }
+
+        if campaign_accessor.cancelled.get() {
+            return Err(CampaignIsCancelled { campaignId: campaign_id }.abi_encode());
+        }
+
+        if !campaign_accessor.retractable.get() {
+            return Err(RetractionNotAllowed { campaignId: campaign_id }.abi_encode());
+        }
+
+        let current_time = U256::from(block::timestamp());
+        let deadline = campaign_accessor.deadline.get();
+        if deadline <= current_time {
+            return Err(DeadlinePassed { deadline, currentTime: current_time }.abi_encode());
+        }
+
+        if amount == U256::from(0) {
+            return Err(ZeroRetraction {}.abi_encode());
+        }
+
+        let contribution = campaign_accessor.contributions.get(donor);
+        if amount > contribution {
+            return Err(RetractionExceedsContribution { contribution, amount }.abi_encode());
+        }
+
+        campaign_accessor.contributions.insert(donor, contribution - amount);
+        if contribution == amount {
+            let donor_count = campaign_accessor.donor_count.get() - U256::from(1);
+            campaign_accessor.donor_count.set(donor_count);
+        }
+        let new_amount_collected = campaign_accessor.amount_collected.get() - amount;
+        campaign_accessor.amount_collected.set(new_amount_collected);
+
+        // Take the retraction out of the donor's most recent donations first, so the donation
+        // history still adds up to `amount_collected`.
+        let mut remaining = amount;
+        let mut index = campaign_accessor.donators.len();
+        while remaining > U256::from(0) && index > 0 {
+            index -= 1;
+            if campaign_accessor.donators.get(index) != Some(donor) {
+                continue;
+            }
+            if let Some(mut donation) = campaign_accessor.donations.setter(index) {
+                let donated = donation.get();
+                let reduction = donated.min(remaining);
+                donation.set(donated - reduction);
+                remaining -= reduction;
+            }
+        }
+
+        let token = campaign_accessor.token.get();
+
+        // Emit DonationWithdrawn event
+        evm::log(DonationWithdrawn {
+            campaignId: campaign_id,
+            donor,
+            amount,
+        });
+
+        self.pay_out(token, donor, amount)?;
+
+        self.unlock();
+        Ok(())
+    }
+
+    pub fn withdraw(&mut self, campaign_id: U256) -> Result<(), Vec<u8>> {
+        self.lock()?;
+
+        let fee_bps = self.fee_bps.get();
+        let paused = self.paused.get();
+        let mut campaign_accessor = self.campaigns.setter(campaign_id);
+
+        let owner = campaign_accessor.owner.get();
+        if owner == Address::default() {
+            return Err(CampaignNotFound { campaignId: campaign_id }.abi_encode());
+        }
+
+        if msg::sender() != owner {
+            return Err(NotCampaignOwner { campaignId: campaign_id, caller: msg::sender() }.abi_encode());
+        }
+
+        if campaign_accessor.cancelled.get() {
+            return Err(CampaignIsCancelled { campaignId: campaign_id }.abi_encode());
+        }
+
+        if !campaign_accessor.milestones.is_empty() {
+            return Err(MilestonesPending { campaignId: campaign_id }.abi_encode());
+        }
+
+        let status = Self::settle_status(&mut campaign_accessor, campaign_id)?;
+
+        // While paused, only campaigns that have already ended can pay out.
+        let current_time = U256::from(block::timestamp());
+        let deadline = campaign_accessor.deadline.get();
+        if paused && deadline > current_time {
+            return Err(ContractPaused {}.abi_encode());
+        }
+
+        let amount_collected = campaign_accessor.amount_collected.get();
+        let funding_model = FundingModel::try_from(campaign_accessor.funding_model.get().to::<u8>())?;
+        if funding_model == FundingModel::AllOrNothing {
+            if deadline > current_time {
+                return Err(CampaignStillActive { deadline, currentTime: current_time }.abi_encode());
+            }
+
+            let target = campaign_accessor.target.get();
+            if amount_collected < target {
+                return Err(TargetNotReached { amountCollected: amount_collected, target }.abi_encode());
+            }
+        }
+
+        let amount_withdrawn = campaign_accessor.amount_withdrawn.get();
+        let amount = amount_collected - amount_withdrawn;
+        if amount == U256::from(0) {
+            // A keep-it-all campaign emptied before its deadline has nothing left to pay once it
+            // ends, so this call only records that everything was withdrawn.
+            if status == CampaignStatus::Successful && amount_withdrawn > U256::from(0) {
+                Self::set_status(&mut campaign_accessor, campaign_id, CampaignStatus::Withdrawn)?;
+                self.unlock();
+                return Ok(());
+            }
+            return Err(NothingToWithdraw { campaignId: campaign_id }.abi_encode());
+        }
+
+        campaign_accessor.amount_withdrawn.set(amount_collected);
+        let payout_count = campaign_accessor.payout_count.get() + U256::from(1);
+        campaign_accessor.payout_count.set(payout_count);
+        if status == CampaignStatus::Successful {
+            Self::set_status(&mut campaign_accessor, campaign_id, CampaignStatus::Withdrawn)?;
+        }
+
+        let token = campaign_accessor.token.get();
+        let (net, fee) = split_fee(amount, fee_bps);
+        let fees_charged = campaign_accessor.fees_charged.get() + fee;
+        campaign_accessor.fees_charged.set(fees_charged);
+
+        // Emit Withdrawn event
+        evm::log(Withdrawn {
+            campaignId: campaign_id,
+            owner,
+            amount: net,
+            fee,
+        });
+
+        self.pay_owner(token, owner, net, fee)?;
+
+        self.unlock();
+        Ok(())
+    }
+
+    pub fn claim_refund(&mut self, campaign_id: U256) -> Result<(), Vec<u8>> {
+        self.lock()?;
+
+        let donor = msg::sender();
+        let mut campaign_accessor = self.campaigns.setter(campaign_id);
+
+        if campaign_accessor.owner.get() == Address::default() {
+            return Err(CampaignNotFound { campaignId: campaign_id }.abi_encode());
+        }
+
+        Self::settle_status(&mut campaign_accessor, campaign_id)?;
+
+        // Cancelled campaigns and rejected milestones make every donation refundable, otherwise
+        // only all-or-nothing campaigns that ended below their target refund donors.
+        let amount_collected = campaign_accessor.amount_collected.get();
+        let funding_model = FundingModel::try_from(campaign_accessor.funding_model.get().to::<u8>())?;
+        if !campaign_accessor.cancelled.get() && !campaign_accessor.milestone_failed.get() {
+            if funding_model == FundingModel::KeepItAll {
+                return Err(NotRefundable { campaignId: campaign_id }.abi_encode());
+            }
+
+            let current_time = U256::from(block::timestamp());
+            let deadline = campaign_accessor.deadline.get();
+            if deadline > current_time {
+                return Err(CampaignStillActive { deadline, currentTime: current_time }.abi_encode());
+            }
+
+            let target = campaign_accessor.target.get();
+            if amount_collected >= target {
+                return Err(TargetReached { amountCollected: amount_collected, target }.abi_encode());
+            }
+        }
+
+        if campaign_accessor.refunded.get(donor) {
+            return Err(AlreadyRefunded { campaignId: campaign_id, donor }.abi_encode());
+        }
+
+        let refund_amount = if funding_model == FundingModel::KeepItAll {
+            // Each withdrawal pays out everything held, so what is left are exactly the donations
+            // made since the last one, and each donor gets theirs back.
+            if campaign_accessor.held_since_payout.get(donor) == campaign_accessor.payout_count.get() {
+                campaign_accessor.held_contributions.get(donor)
+            } else {
+                U256::from(0)
+            }
+        } else {
+            // Milestones are only released after the deadline, so every donor shares whatever
+            // has not been released to the owner in proportion to what they gave.
+            let contribution = campaign_accessor.contributions.get(donor);
+            let remaining = amount_collected - campaign_accessor.amount_withdrawn.get();
+            if contribution == U256::from(0) {
+                U256::from(0)
+            } else {
+                contribution * remaining / amount_collected
+            }
+        };
+
+        if refund_amount == U256::from(0) {
+            return Err(NothingToRefund { campaignId: campaign_id, donor }.abi_encode());
+        }
+
+        campaign_accessor.refunded.insert(donor, true);
+
+        let token = campaign_accessor.token.get();
+
+        // Emit RefundClaimed event
+        evm::log(RefundClaimed {
+            campaignId: campaign_id,
+            donor,
+            amount: refund_amount,
+        });
+
+        self.pay_out(token, donor, refund_amount)?;
+
+        self.unlock();
+        Ok(())
+    }
+
+    pub fn cancel_campaign(&mut self, campaign_id: U256) -> Result<(), Vec<u8>> {
+        let mut campaign_accessor = self.campaigns.setter(campaign_id);
+
+        let owner = campaign_accessor.owner.get();
+        if owner == Address::default() {
+            return Err(CampaignNotFound { campaignId: campaign_id }.abi_encode());
+        }
+
+        if msg::sender() != owner {
+            return Err(NotCampaignOwner { campaignId: campaign_id, caller: msg::sender() }.abi_encode());
+        }
+
+        if campaign_accessor.cancelled.get() {
+            return Err(CampaignIsCancelled { campaignId: campaign_id }.abi_encode());
+        }
+
+        Self::settle_status(&mut campaign_accessor, campaign_id)?;
+        Self::set_status(&mut campaign_accessor, campaign_id, CampaignStatus::Cancelled)?;
+        campaign_accessor.cancelled.set(true);
+
+        // Emit CampaignCancelled event
+        evm::log(CampaignCancelled {
+            campaignId: campaign_id,
+            owner,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_campaign_details(
+        &mut self,
+        campaign_id: U256,
+        title: String,
+        description: String,
+        image: String,
+    ) -> Result<(), Vec<u8>> {
+        let mut campaign_accessor = self.campaigns.setter(campaign_id);
+
+        let owner = campaign_accessor.owner.get();
+        if owner == Address::default() {
+            return Err(CampaignNotFound { campaignId: campaign_id }.abi_encode());
+        }
+
+        if msg::sender() != owner {
+            return Err(NotCampaignOwner { campaignId: campaign_id, caller: msg::sender() }.abi_encode());
+        }
+
+        if campaign_accessor.cancelled.get() {
+            return Err(CampaignIsCancelled { campaignId: campaign_id }.abi_encode());
+        }
+
+        let old_content_hash = content_hash(
+            campaign_accessor.title.get_string(),
+            campaign_accessor.description.get_string(),
+            campaign_accessor.image.get_string(),
+        );
+
+        campaign_accessor.title.set_str(&title);
+        campaign_accessor.description.set_str(&description);
+        campaign_accessor.image.set_str(&image);
+
+        let revision = campaign_accessor.revision.get() + U256::from(1);
+        campaign_accessor.revision.set(revision);
+
+        // Emit CampaignUpdated event
+        evm::log(CampaignUpdated {
+            campaignId: campaign_id,
+            revision,
+            oldContentHash: old_content_hash,
+            newContentHash: content_hash(title, description, image),
+        });
+
+        Ok(())
+    }
+
+    #[view]
+    pub fn get_revision(&self, campaign_id: U256) -> U256 {
+        self.campaigns.get(campaign_id).revision.get()
+    }
+
+    pub fn set_allowlist_root(&mut self, campaign_id: U256, root: FixedBytes<32>) -> Result<(), Vec<u8>> {
+        let mut campaign_accessor = self.campaigns.setter(campaign_id);
+
+        let owner = campaign_accessor.owner.get();
+        if owner == Address::default() {
+            return Err(CampaignNotFound { campaignId: campaign_id }.abi_encode());
+        }
+
+        if msg::sender() != owner {
+            return Err(NotCampaignOwner { campaignId: campaign_id, caller: msg::sender() }.abi_encode());
+        }
+
+        if campaign_accessor.cancelled.get() {
+            return Err(CampaignIsCancelled { campaignId: campaign_id }.abi_encode());
+        }
+
+        // Only future donations are checked against the new root.
+        let old_root = campaign_accessor.allowlist_root.get();
+        campaign_accessor.allowlist_root.set(root);
+
+        // Emit AllowlistRootUpdated event
+        evm::log(AllowlistRootUpdated {
+            campaignId: campaign_id,
+            oldRoot: old_root,
+            newRoot: root,
+        });
+
+        Ok(())
+    }
+
+    pub fn extend_deadline(&mut self, campaign_id: U256, new_deadline: U256) -> Result<(), Vec<u8>> {
+        let mut campaign_accessor = self.campaigns.setter(campaign_id);
+
+        let owner = campaign_accessor.owner.get();
+        if owner == Address::default() {
+            return Err(CampaignNotFound { campaignId: campaign_id }.abi_encode());
+        }
+
+        if msg::sender() != owner {
+            return Err(NotCampaignOwner { campaignId: campaign_id, caller: msg::sender() }.abi_encode());
+        }
+
+        if campaign_accessor.cancelled.get() {
+            return Err(CampaignIsCancelled { campaignId: campaign_id }.abi_encode());
+        }
+
+        let current_time = U256::from(block::timestamp());
+        let deadline = campaign_accessor.deadline.get();
+        if deadline <= current_time {
+            return Err(DeadlinePassed { deadline, currentTime: current_time }.abi_encode());
+        }
+
+        if new_deadline <= deadline {
+            return Err(DeadlineNotExtended { currentDeadline: deadline, newDeadline: new_deadline }.abi_encode());
+        }
+
+        Self::settle_status(&mut campaign_accessor, campaign_id)?;
+
+        // Extensions are capped against the original deadline so they cannot be chained forever.
+        let max_deadline = campaign_accessor.original_deadline.get() + U256::from(MAX_DEADLINE_EXTENSION);
+        if new_deadline > max_deadline {
+            return Err(DeadlineExtensionTooLong { newDeadline: new_deadline, maxDeadline: max_deadline }.abi_encode());
+        }
+
+        campaign_accessor.deadline.set(new_deadline);
+
+        // Emit DeadlineExtended event
+        evm::log(DeadlineExtended {
+            campaignId: campaign_id,
+            oldDeadline: deadline,
+            newDeadline: new_deadline,
+        });
+
+        Ok(())
+    }
+
+    pub fn close_early(&mut self, campaign_id: U256) -> Result<(), Vec<u8>> {
+        let mut campaign_accessor = self.campaigns.setter(campaign_id);
+
+        let owner = campaign_accessor.owner.get();
+        if owner == Address::default() {
+            return Err(CampaignNotFound { campaignId: campaign_id }.abi_encode());
+        }
+
+        if msg::sender() != owner {
+            return Err(NotCampaignOwner { campaignId: campaign_id, caller: msg::sender() }.abi_encode());
+        }
+
+        if campaign_accessor.cancelled.get() {
+            return Err(CampaignIsCancelled { campaignId: campaign_id }.abi_encode());
+        }
+
+        let current_time = U256::from(block::timestamp());
+        let deadline = campaign_accessor.deadline.get();
+        if deadline <= current_time {
+            return Err(DeadlinePassed { deadline, currentTime: current_time }.abi_encode());
+        }
+
+        let amount_collected = campaign_accessor.amount_collected.get();
+        let target = campaign_accessor.target.get();
+        if amount_collected < target {
+            return Err(TargetNotReached { amountCollected: amount_collected, target }.abi_encode());
+        }
+
+        // Ending the campaign now stops donations and unlocks withdrawals and milestone votes.
+        Self::settle_status(&mut campaign_accessor, campaign_id)?;
+        campaign_accessor.deadline.set(current_time);
+        Self::set_status(&mut campaign_accessor, campaign_id, CampaignStatus::Successful)?;
+
+        // Emit CampaignClosedEarly event
+        evm::log(CampaignClosedEarly {
+            campaignId: campaign_id,
+            deadline: current_time,
+            amountCollected: amount_collected,
+        });
+
+        Ok(())
+    }
+
+    pub fn propose_milestone(&mut self, campaign_id: U256) -> Result<(), Vec<u8>> {
+        let mut campaign_accessor = self.campaigns.setter(campaign_id);
+
+        let owner = campaign_accessor.owner.get();
+        if owner == Address::default() {
+            return Err(CampaignNotFound { campaignId: campaign_id }.abi_encode());
+        }
+
+        if msg::sender() != owner {
+            return Err(NotCampaignOwner { campaignId: campaign_id, caller: msg::sender() }.abi_encode());
+        }
+
+        let milestone_index = campaign_accessor.current_milestone.get();
+        Self::check_milestone_pending(&campaign_accessor, campaign_id, milestone_index)?;
+
+        let current_time = U256::from(block::timestamp());
+        let deadline = campaign_accessor.deadline.get();
+        if deadline > current_time {
+            return Err(CampaignStillActive { deadline, currentTime: current_time }.abi_encode());
+        }
+
+        let amount_collected = campaign_accessor.amount_collected.get();
+        let target = campaign_accessor.target.get();
+        if amount_collected < target {
+            return Err(TargetNotReached { amountCollected: amount_collected, target }.abi_encode());
+        }
+
+        Self::settle_status(&mut campaign_accessor, campaign_id)?;
+
+        let proposal_deadline = Self::proposal_deadline(&campaign_accessor, milestone_index);
+
+        let mut milestone = campaign_accessor
+            .milestones
+            .setter(milestone_index.to::<usize>())
+            .ok_or_else(|| AllMilestonesReleased { campaignId: campaign_id }.abi_encode())?;
+        if milestone.voting_deadline.get() != U256::from(0) {
+            return Err(MilestoneAlreadyProposed { campaignId: campaign_id, milestoneIndex: milestone_index }.abi_encode());
+        }
+
+        // Past the proposal period the campaign can only be failed with `fail_missed_milestone`.
+        if proposal_deadline <= current_time {
+            return Err(ProposalPeriodOver { proposalDeadline: proposal_deadline, currentTime: current_time }.abi_encode());
+        }
+
+        let voting_deadline = current_time + U256::from(MILESTONE_VOTING_PERIOD);
+        milestone.voting_deadline.set(voting_deadline);
+
+        // Emit MilestoneProposed event
+        evm::log(MilestoneProposed {
+            campaignId: campaign_id,
+            milestoneIndex: milestone_index,
+            amount: milestone.amount.get(),
+            descriptionHash: milestone.description_hash.get(),
+            votingDeadline: voting_deadline,
+        });
+
+        Ok(())
+    }
+
+    /// Fails a campaign whose owner let the proposal period of the next milestone pass without
+    /// proposing it, making what is left refundable. Anyone can call it.
+    pub fn fail_missed_milestone(&mut self, campaign_id: U256) -> Result<(), Vec<u8>> {
+        let mut campaign_accessor = self.campaigns.setter(campaign_id);
+
+        if campaign_accessor.owner.get() == Address::default() {
+            return Err(CampaignNotFound { campaignId: campaign_id }.abi_encode());
+        }
+
+        let milestone_index = campaign_accessor.current_milestone.get();
+        Self::check_milestone_pending(&campaign_accessor, campaign_id, milestone_index)?;
+
+        // Campaigns that missed their target are already refundable.
+        let amount_collected = campaign_accessor.amount_collected.get();
+        let target = campaign_accessor.target.get();
+        if amount_collected < target {
+            return Err(TargetNotReached { amountCollected: amount_collected, target }.abi_encode());
+        }
+
+        let milestone = campaign_accessor
+            .milestones
+            .get(milestone_index.to::<usize>())
+            .ok_or_else(|| AllMilestonesReleased { campaignId: campaign_id }.abi_encode())?;
+        if milestone.voting_deadline.get() != U256::from(0) {
+            return Err(MilestoneAlreadyProposed { campaignId: campaign_id, milestoneIndex: milestone_index }.abi_encode());
+        }
+
+        let current_time = U256::from(block::timestamp());
+        let proposal_deadline = Self::proposal_deadline(&campaign_accessor, milestone_index);
+        if proposal_deadline > current_time {
+            return Err(ProposalPeriodOpen { proposalDeadline: proposal_deadline, currentTime: current_time }.abi_encode());
+        }
+
+        Self::settle_status(&mut campaign_accessor, campaign_id)?;
+        campaign_accessor.milestone_failed.set(true);
+        Self::set_status(&mut campaign_accessor, campaign_id, CampaignStatus::Failed)?;
+
+        // Emit MilestoneMissed event
+        evm::log(MilestoneMissed {
+            campaignId: campaign_id,
+            milestoneIndex: milestone_index,
+            proposalDeadline: proposal_deadline,
+        });
+
+        Ok(())
+    }
+
+    pub fn vote_on_milestone(&mut self, campaign_id: U256, approve: bool) -> Result<(), Vec<u8>> {
+        let voter = msg::sender();
+        let mut campaign_accessor = self.campaigns.setter(campaign_id);
+
+        if campaign_accessor.owner.get() == Address::default() {
+            return Err(CampaignNotFound { campaignId: campaign_id }.abi_encode());
+        }
+
+        let milestone_index = campaign_accessor.current_milestone.get();
+        Self::check_milestone_pending(&campaign_accessor, campaign_id, milestone_index)?;
 
-#[external]
-impl CrowdFunding {
-    pub fn create_campaign(
-        &mut self,
-        owner: Address,
-        title: String,
-        description: String,
-        target: U256,
-        deadline: U256,
-        image: String,
-    ) -> U256 {
-        let number_of_campaigns = self.no_of_campaigns.get();
+        // Votes are weighted by how much the voter has donated to the campaign.
+        let weight = campaign_accessor.contributions.get(voter);
+        if weight == U256::from(0) {
+            return Err(NotADonor { campaignId: campaign_id, caller: voter }.abi_encode());
+        }
 
-        let current_time = U256::from(block::timestamp());
-        if deadline <= current_time {
-            console!("Error: The deadline must be in the future.");
-            return U256::from(0);
+        let mut milestone = campaign_accessor
+            .milestones
+            .setter(milestone_index.to::<usize>())
+            .ok_or_else(|| AllMilestonesReleased { campaignId: campaign_id }.abi_encode())?;
+
+        let voting_deadline = milestone.voting_deadline.get();
+        if voting_deadline == U256::from(0) {
+            return Err(MilestoneNotProposed { campaignId: campaign_id, milestoneIndex: milestone_index }.abi_encode());
         }
 
-        let mut campaign_accessor = self.campaigns.setter(number_of_campaigns);
+        let current_time = U256::from(block::timestamp());
+        if voting_deadline <= current_time {
+            return Err(VotingClosed { votingDeadline: voting_deadline, currentTime: current_time }.abi_encode());
+        }
 
-        campaign_accessor.owner.set(owner);
-        campaign_accessor.title.set_str(&title);
-        campaign_accessor.description.set_str(&description);
-        campaign_accessor.target.set(target);
-        campaign_accessor.deadline.set(deadline);
-        campaign_accessor.amount_collected.set(U256::from(0));
-        campaign_accessor.image.set_str(&image);
-        // StorageVec is automatically initialized, no need to call initialize()
+        if milestone.voted.get(voter) {
+            return Err(AlreadyVoted { campaignId: campaign_id, milestoneIndex: milestone_index, voter }.abi_encode());
+        }
 
-        self.no_of_campaigns.set(number_of_campaigns + U256::from(1));
+        milestone.voted.insert(voter, true);
+        if approve {
+            let approvals = milestone.approvals.get() + weight;
+            milestone.approvals.set(approvals);
+        } else {
+            let rejections = milestone.rejections.get() + weight;
+            milestone.rejections.set(rejections);
+        }
 
-          // Emit CampaignCreated event
-          evm::log(CampaignCreated {
-            campaignId: number_of_campaigns,
-            owner,
-            title,
-            target,
-            deadline,
+        // Emit MilestoneVoted event
+        evm::log(MilestoneVoted {
+            campaignId: campaign_id,
+            milestoneIndex: milestone_index,
+            voter,
+            approve,
+            weight,
         });
 
-        number_of_campaigns
+        Ok(())
     }
 
-    #[payable]
-    pub fn donate_to_campaign(&mut self, campaign_id: U256) {
+    pub fn release_milestone(&mut self, campaign_id: U256) -> Result<(), Vec<u8>> {
+        self.lock()?;
+
+        let fee_bps = self.fee_bps.get();
         let mut campaign_accessor = self.campaigns.setter(campaign_id);
 
-        if campaign_accessor.owner.get() == Address::default() {
-            console!("Error: Campaign does not exist.");
-            return;
+        let owner = campaign_accessor.owner.get();
+        if owner == Address::default() {
+            return Err(CampaignNotFound { campaignId: campaign_id }.abi_encode());
         }
 
-        let current_time = U256::from(block::timestamp());
-        if campaign_accessor.deadline.get() <= current_time {
-            console!("Error: The deadline for this campaign has passed.");
-            return;
+        let milestone_index = campaign_accessor.current_milestone.get();
+        Self::check_milestone_pending(&campaign_accessor, campaign_id, milestone_index)?;
+
+        Self::settle_status(&mut campaign_accessor, campaign_id)?;
+
+        let milestone_count = U256::from(campaign_accessor.milestones.len());
+        let remaining = campaign_accessor.amount_collected.get() - campaign_accessor.amount_withdrawn.get();
+
+        let milestone = campaign_accessor
+            .milestones
+            .get(milestone_index.to::<usize>())
+            .ok_or_else(|| AllMilestonesReleased { campaignId: campaign_id }.abi_encode())?;
+
+        let voting_deadline = milestone.voting_deadline.get();
+        if voting_deadline == U256::from(0) {
+            return Err(MilestoneNotProposed { campaignId: campaign_id, milestoneIndex: milestone_index }.abi_encode());
         }
 
-        let donation_amount = msg::value();
-        if donation_amount == U256::from(0) {
-            console!("Error: Donation amount must be greater than zero.");
-            return;
+        let current_time = U256::from(block::timestamp());
+        if voting_deadline > current_time {
+            return Err(VotingStillOpen { votingDeadline: voting_deadline, currentTime: current_time }.abi_encode());
         }
 
-        let new_amount_collected = campaign_accessor.amount_collected.get() + donation_amount;
-        campaign_accessor.amount_collected.set(new_amount_collected);
-        campaign_accessor.donators.push(msg::sender());
-        campaign_accessor.donations.push(donation_amount);
+        let milestone_amount = milestone.amount.get();
+        let approvals = milestone.approvals.get();
+        let rejections = milestone.rejections.get();
+        if approvals <= rejections {
+            // A rejected milestone freezes the campaign and makes what is left refundable.
+            campaign_accessor.milestone_failed.set(true);
+            Self::set_status(&mut campaign_accessor, campaign_id, CampaignStatus::Failed)?;
 
-        console!("Donation of {:?} received for campaign ID: {:?}", donation_amount, campaign_id);
+            // Emit MilestoneRejected event
+            evm::log(MilestoneRejected {
+                campaignId: campaign_id,
+                milestoneIndex: milestone_index,
+                approvals,
+                rejections,
+            });
 
-        if let Err(_) = transfer_eth(campaign_accessor.owner.get(), msg::value()) {
-            console!("Error: Failed to transfer ETH to campaign owner.");
+            self.unlock();
+            return Ok(());
         }
 
-        // Emit DonationMade event
-        evm::log(DonationMade {
+        // The last tranche also releases anything raised above the target.
+        let last_milestone = milestone_index + U256::from(1) == milestone_count;
+        let amount = if last_milestone { remaining } else { milestone_amount };
+
+        let amount_withdrawn = campaign_accessor.amount_withdrawn.get() + amount;
+        campaign_accessor.amount_withdrawn.set(amount_withdrawn);
+        campaign_accessor.current_milestone.set(milestone_index + U256::from(1));
+        if last_milestone {
+            Self::set_status(&mut campaign_accessor, campaign_id, CampaignStatus::Withdrawn)?;
+        }
+
+        let token = campaign_accessor.token.get();
+        let (net, fee) = split_fee(amount, fee_bps);
+        let fees_charged = campaign_accessor.fees_charged.get() + fee;
+        campaign_accessor.fees_charged.set(fees_charged);
+
+        // Emit MilestoneReleased event
+        evm::log(MilestoneReleased {
             campaignId: campaign_id,
-            donor: msg::sender(),
-            amount: donation_amount,
+            milestoneIndex: milestone_index,
+            amount: net,
+            fee,
         });
+
+        self.pay_owner(token, owner, net, fee)?;
+
+        self.unlock();
+        Ok(())
+    }
+
+    #[view]
+    pub fn get_milestones(&self, campaign_id: U256) -> MilestonesView {
+        let campaign_accessor = self.campaigns.get(campaign_id);
+        let mut amounts = Vec::new();
+        let mut description_hashes = Vec::new();
+        let mut voting_deadlines = Vec::new();
+        let mut approvals = Vec::new();
+        let mut rejections = Vec::new();
+
+        for i in 0..campaign_accessor.milestones.len() {
+            if let Some(milestone) = campaign_accessor.milestones.get(i) {
+                amounts.push(milestone.amount.get());
+                description_hashes.push(milestone.description_hash.get());
+                voting_deadlines.push(milestone.voting_deadline.get());
+                approvals.push(milestone.approvals.get());
+                rejections.push(milestone.rejections.get());
+            }
+        }
+
+        (amounts, description_hashes, voting_deadlines, approvals, rejections)
+    }
+
+    #[view]
+    pub fn get_contribution(&self, campaign_id: U256, donor: Address) -> U256 {
+        self.campaigns.get(campaign_id).contributions.get(donor)
+    }
+
+    #[view]
+    pub fn get_donor_count(&self, campaign_id: U256) -> U256 {
+        self.campaigns.get(campaign_id).donor_count.get()
+    }
+
+    /// Returns the campaign's `CampaignStatus` as its numeric value.
+    #[view]
+    pub fn get_campaign_status(&self, campaign_id: U256) -> Result<u8, Vec<u8>> {
+        let campaign_accessor = self.campaigns.get(campaign_id);
+        if campaign_accessor.owner.get() == Address::default() {
+            return Err(CampaignNotFound { campaignId: campaign_id }.abi_encode());
+        }
+        Ok(Self::current_status(&campaign_accessor)? as u8)
+    }
+
+    /// Returns up to `limit` donations of a campaign starting at `offset`, plus the total
+    /// number of donations so callers know when to stop paging.
+    #[view]
+    pub fn get_donators_page(&self, campaign_id: U256, offset: U256, limit: U256) -> (Vec<Address>, Vec<U256>, U256) {
+        let campaign_accessor = self.campaigns.get(campaign_id);
+        let total = U256::from(campaign_accessor.donators.len());
+        let mut donators = Vec::new();
+        let mut donations = Vec::new();
+
+        for i in page_range(offset, limit, total) {
+            if let Some(donator) = campaign_accessor.donators.get(i) {
+                donators.push(donator);
+            }
+            if let Some(donation) = campaign_accessor.donations.get(i) {
+                donations.push(donation);
+            }
+        }
+        (donators, donations, total)
     }
 
     #[view]
@@ -139,18 +1449,64 @@ impl CrowdFunding {
         (donators, donations)
     }
 
+    /// Returns up to `limit` ids of the campaigns created for `owner`, starting at `offset`, plus
+    /// the total number of them.
+    #[view]
+    pub fn get_campaigns_by_owner(&self, owner: Address, offset: U256, limit: U256) -> (Vec<U256>, U256) {
+        let campaign_ids = self.campaigns_by_owner.get(owner);
+        let total = U256::from(campaign_ids.len());
+        let mut page = Vec::new();
+
+        for i in page_range(offset, limit, total) {
+            if let Some(campaign_id) = campaign_ids.get(i) {
+                page.push(campaign_id);
+            }
+        }
+        (page, total)
+    }
+
+    /// Returns up to `limit` ids of the campaigns `donor` has donated to, starting at `offset`,
+    /// plus the total number of them. Retracting a donation does not remove the campaign.
+    #[view]
+    pub fn get_campaigns_by_donor(&self, donor: Address, offset: U256, limit: U256) -> (Vec<U256>, U256) {
+        let campaign_ids = self.campaigns_by_donor.get(donor);
+        let total = U256::from(campaign_ids.len());
+        let mut page = Vec::new();
+
+        for i in page_range(offset, limit, total) {
+            if let Some(campaign_id) = campaign_ids.get(i) {
+                page.push(campaign_id);
+            }
+        }
+        (page, total)
+    }
+
+    /// Returns one campaign; see `CampaignView` for the order of its fields.
+    #[view]
+    pub fn get_campaign(&self, campaign_id: U256) -> Result<CampaignView, Vec<u8>> {
+        let campaign_accessor = self.campaigns.get(campaign_id);
+        let owner = campaign_accessor.owner.get();
+        if owner == Address::default() {
+            return Err(CampaignNotFound { campaignId: campaign_id }.abi_encode());
+        }
+
+        Ok((
+            owner,
+            campaign_accessor.title.get_string(),
+            campaign_accessor.description.get_string(),
+            campaign_accessor.target.get(),
+            campaign_accessor.deadline.get(),
+            campaign_accessor.amount_collected.get(),
+            campaign_accessor.image.get_string(),
+            campaign_accessor.donor_count.get(),
+            Self::current_status(&campaign_accessor)? as u8,
+        ))
+    }
+
     #[view]
-    pub fn get_campaigns(&self) -> (
-        Vec<Address>,
-        Vec<String>,
-        Vec<String>,
-        Vec<U256>,
-        Vec<U256>,
-        Vec<String>,
-        Vec<Vec<Address>>,
-        Vec<Vec<U256>>,
-    ) {
+    pub fn get_campaigns(&self) -> CampaignsView {
         let number_of_campaigns = self.no_of_campaigns.get();
+        let fee_bps = self.fee_bps.get();
         let mut owners = Vec::new();
         let mut titles = Vec::new();
         let mut descriptions = Vec::new();
@@ -159,6 +1515,10 @@ impl CrowdFunding {
         let mut images = Vec::new();
         let mut donators = Vec::new();
         let mut donations = Vec::new();
+        let mut funding_models = Vec::new();
+        let mut tokens = Vec::new();
+        let mut gross_amounts = Vec::new();
+        let mut net_amounts = Vec::new();
 
         for i in 0..number_of_campaigns.as_limbs()[0] {
             let campaign_accessor = self.campaigns.get(U256::from(i));
@@ -168,6 +1528,11 @@ impl CrowdFunding {
             targets.push(campaign_accessor.target.get());
             deadlines.push(campaign_accessor.deadline.get());
             images.push(campaign_accessor.image.get_string());
+            funding_models.push(campaign_accessor.funding_model.get().to::<u8>());
+            tokens.push(campaign_accessor.token.get());
+
+            gross_amounts.push(campaign_accessor.amount_collected.get());
+            net_amounts.push(Self::net_amount(&campaign_accessor, fee_bps));
 
             let mut campaign_donators = Vec::new();
             let mut campaign_donations = Vec::new();
@@ -183,6 +1548,429 @@ impl CrowdFunding {
             donations.push(campaign_donations);
         }
 
-        (owners, titles, descriptions, targets, deadlines, images, donators, donations)
+        (owners, titles, descriptions, targets, deadlines, images, donators, donations, funding_models, tokens, gross_amounts, net_amounts)
+    }
+
+    /// Same as `get_campaigns` for up to `limit` campaigns starting at id `offset`, without the
+    /// donor lists (page through those with `get_donators_page`). The last value is the total
+    /// number of campaigns.
+    #[view]
+    pub fn get_campaigns_page(&self, offset: U256, limit: U256) -> CampaignsPage {
+        let number_of_campaigns = self.no_of_campaigns.get();
+        let fee_bps = self.fee_bps.get();
+        let mut owners = Vec::new();
+        let mut titles = Vec::new();
+        let mut descriptions = Vec::new();
+        let mut targets = Vec::new();
+        let mut deadlines = Vec::new();
+        let mut images = Vec::new();
+        let mut funding_models = Vec::new();
+        let mut tokens = Vec::new();
+        let mut gross_amounts = Vec::new();
+        let mut net_amounts = Vec::new();
+
+        for i in page_range(offset, limit, number_of_campaigns) {
+            let campaign_accessor = self.campaigns.get(U256::from(i));
+            owners.push(campaign_accessor.owner.get());
+            titles.push(campaign_accessor.title.get_string());
+            descriptions.push(campaign_accessor.description.get_string());
+            targets.push(campaign_accessor.target.get());
+            deadlines.push(campaign_accessor.deadline.get());
+            images.push(campaign_accessor.image.get_string());
+            funding_models.push(campaign_accessor.funding_model.get().to::<u8>());
+            tokens.push(campaign_accessor.token.get());
+
+            gross_amounts.push(campaign_accessor.amount_collected.get());
+            net_amounts.push(Self::net_amount(&campaign_accessor, fee_bps));
+        }
+
+        (owners, titles, descriptions, targets, deadlines, images, funding_models, tokens, gross_amounts, net_amounts, number_of_campaigns)
+    }
+}
+
+impl CrowdFunding {
+    /// Takes the reentrancy lock guarding the entrypoints that move ETH, reverting if a
+    /// receiver tries to call back into one of them.
+    fn lock(&mut self) -> Result<(), Vec<u8>> {
+        if self.locked.get() {
+            return Err(ReentrantCall {}.abi_encode());
+        }
+        self.locked.set(true);
+        Ok(())
+    }
+
+    fn unlock(&mut self) {
+        self.locked.set(false);
+    }
+
+    fn when_not_paused(&self) -> Result<(), Vec<u8>> {
+        if self.paused.get() {
+            return Err(ContractPaused {}.abi_encode());
+        }
+        Ok(())
+    }
+
+    fn only_role(&self, role: FixedBytes<32>) -> Result<(), Vec<u8>> {
+        let account = msg::sender();
+        if !self.roles.get(role).get(account) {
+            return Err(MissingRole { role, account }.abi_encode());
+        }
+        Ok(())
+    }
+
+    /// Grants or revokes `role` for `account`, logging only when membership actually changes.
+    fn set_role(&mut self, role: FixedBytes<32>, account: Address, granted: bool) {
+        let mut members = self.roles.setter(role);
+        if members.get(account) == granted {
+            return;
+        }
+        members.insert(account, granted);
+
+        if granted {
+            // Emit RoleGranted event
+            evm::log(RoleGranted { role, account, sender: msg::sender() });
+        } else {
+            // Emit RoleRevoked event
+            evm::log(RoleRevoked { role, account, sender: msg::sender() });
+        }
+    }
+
+    /// Checks that `campaign_id` accepts a donation of `amount` in `token` from the caller and
+    /// records it, returning how much was accepted under the campaign's hard cap. Moving the
+    /// funds into the contract, and returning any surplus, is left to the calling entrypoint.
+    fn record_donation(
+        &mut self,
+        campaign_id: U256,
+        token: Address,
+        amount: U256,
+        proof: &[FixedBytes<32>],
+    ) -> Result<U256, Vec<u8>> {
+        self.when_not_paused()?;
+
+        let donor = msg::sender();
+        let mut campaign_accessor = self.campaigns.setter(campaign_id);
+
+        if campaign_accessor.owner.get() == Address::default() {
+            return Err(CampaignNotFound { campaignId: campaign_id }.abi_encode());
+        }
+
+        if campaign_accessor.cancelled.get() {
+            return Err(CampaignIsCancelled { campaignId: campaign_id }.abi_encode());
+        }
+
+        let current_time = U256::from(block::timestamp());
+        let deadline = campaign_accessor.deadline.get();
+        if deadline <= current_time {
+            return Err(DeadlinePassed { deadline, currentTime: current_time }.abi_encode());
+        }
+
+        let expected = campaign_accessor.token.get();
+        if token != expected {
+            return Err(WrongDenomination { expected, provided: token }.abi_encode());
+        }
+
+        // Settle before counting the donation so that a campaign created before statuses were
+        // stored only records what it had already forwarded as withdrawn.
+        Self::settle_status(&mut campaign_accessor, campaign_id)?;
+
+        let allowlist_root = campaign_accessor.allowlist_root.get();
+        if allowlist_root != FixedBytes::ZERO {
+            let leaf = crypto::keccak(donor.as_slice());
+            if !verify_merkle_proof(proof, allowlist_root, leaf) {
+                return Err(NotAllowlisted { campaignId: campaign_id, donor }.abi_encode());
+            }
+        }
+
+        if amount == U256::from(0) {
+            return Err(ZeroDonation {}.abi_encode());
+        }
+
+        // With a hard cap, only what still fits under it is accepted and filling it closes the campaign.
+        let amount_collected = campaign_accessor.amount_collected.get();
+        let hard_cap = campaign_accessor.hard_cap.get();
+        let mut accepted = amount;
+        let mut cap_reached = false;
+        if hard_cap != U256::from(0) && amount_collected + amount >= hard_cap {
+            accepted = hard_cap - amount_collected;
+            cap_reached = true;
+        }
+
+        // The donation that fills the cap may be smaller than the minimum.
+        let min_donation = campaign_accessor.min_donation.get();
+        if accepted < min_donation && !cap_reached {
+            return Err(DonationBelowMinimum { amount: accepted, minDonation: min_donation }.abi_encode());
+        }
+
+        let max_donation = campaign_accessor.max_donation.get();
+        if max_donation != U256::from(0) && accepted > max_donation {
+            return Err(DonationAboveMaximum { amount: accepted, maxDonation: max_donation }.abi_encode());
+        }
+
+        let contribution = campaign_accessor.contributions.get(donor);
+        let max_per_donor = campaign_accessor.max_per_donor.get();
+        if max_per_donor != U256::from(0) && contribution + accepted > max_per_donor {
+            return Err(DonorCapExceeded { contribution: contribution + accepted, maxPerDonor: max_per_donor }.abi_encode());
+        }
+
+        let new_amount_collected = amount_collected + accepted;
+        campaign_accessor.amount_collected.set(new_amount_collected);
+        campaign_accessor.donators.push(donor);
+        campaign_accessor.donations.push(accepted);
+
+        if contribution == U256::from(0) {
+            let donor_count = campaign_accessor.donor_count.get() + U256::from(1);
+            campaign_accessor.donor_count.set(donor_count);
+        }
+        campaign_accessor.contributions.insert(donor, contribution + accepted);
+
+        // Keep-it-all campaigns pay out everything held at each withdrawal, so only what a donor
+        // gave since the last one is still theirs to reclaim if the campaign is cancelled.
+        let funding_model = FundingModel::try_from(campaign_accessor.funding_model.get().to::<u8>())?;
+        if funding_model == FundingModel::KeepItAll {
+            let payout_count = campaign_accessor.payout_count.get();
+            let held = if campaign_accessor.held_since_payout.get(donor) == payout_count {
+                campaign_accessor.held_contributions.get(donor)
+            } else {
+                U256::from(0)
+            };
+            campaign_accessor.held_contributions.insert(donor, held + accepted);
+            campaign_accessor.held_since_payout.insert(donor, payout_count);
+        }
+
+        // Contributions can drop back to zero through retractions, so a separate flag keeps the
+        // donor index free of duplicates.
+        if !campaign_accessor.indexed_donors.get(donor) {
+            campaign_accessor.indexed_donors.insert(donor, true);
+            self.campaigns_by_donor.setter(donor).push(campaign_id);
+        }
+
+        // Emit DonationMade event
+        evm::log(DonationMade {
+            campaignId: campaign_id,
+            donor,
+            amount: accepted,
+        });
+
+        if cap_reached {
+            campaign_accessor.deadline.set(current_time);
+            Self::set_status(&mut campaign_accessor, campaign_id, CampaignStatus::Successful)?;
+
+            // Emit HardCapReached event
+            evm::log(HardCapReached {
+                campaignId: campaign_id,
+                amountCollected: new_amount_collected,
+            });
+        }
+
+        Ok(accepted)
+    }
+
+    /// Pulls `amount` of `token` from `from` into the contract, which needs an allowance.
+    fn pull_tokens(&mut self, token: Address, from: Address, amount: U256) -> Result<(), Vec<u8>> {
+        let calldata = transferFromCall { from, to: contract::address(), amount }.abi_encode();
+        self.call_token(token, &calldata)
+    }
+
+    /// Pays a campaign owner their share of released funds and the platform fee to the treasury.
+    fn pay_owner(&mut self, token: Address, owner: Address, net: U256, fee: U256) -> Result<(), Vec<u8>> {
+        if fee > U256::from(0) {
+            let treasury = self.treasury.get();
+            self.pay_out(token, treasury, fee)?;
+        }
+        self.pay_out(token, owner, net)
+    }
+
+    /// Sends `amount` of `token` to `to`, using a plain ETH transfer when `token` is the zero address.
+    fn pay_out(&mut self, token: Address, to: Address, amount: U256) -> Result<(), Vec<u8>> {
+        if token == Address::default() {
+            return transfer_eth(to, amount);
+        }
+
+        let calldata = transferCall { to, amount }.abi_encode();
+        self.call_token(token, &calldata)
+    }
+
+    /// Makes a `transfer` or `transferFrom` call to `token` with the checks of OpenZeppelin's
+    /// `SafeERC20`: the call must not revert, and the token must either return `true` or, like
+    /// USDT, return nothing at all. An empty return only counts if `token` has code, since calls
+    /// to other accounts always succeed.
+    fn call_token(&mut self, token: Address, calldata: &[u8]) -> Result<(), Vec<u8>> {
+        let returned = call::call(Call::new_in(self), token, calldata)
+            .map_err(|_| TokenTransferFailed { token }.abi_encode())?;
+        let succeeded = if returned.is_empty() {
+            token.has_code()
+        } else {
+            bool::abi_decode(&returned, false).unwrap_or(false)
+        };
+        if !succeeded {
+            return Err(TokenTransferFailed { token }.abi_encode());
+        }
+        Ok(())
+    }
+
+    /// What the owner receives from everything the campaign collected: the payouts made so far,
+    /// net of the fees actually charged, plus what is still held minus the current fee. Campaigns
+    /// created before statuses were stored and not settled since were paid out in full, fee-free.
+    fn net_amount(campaign: &CampaignStorage, fee_bps: U256) -> U256 {
+        if campaign.status.get().to::<u8>() == CampaignStatus::Draft as u8 {
+            return campaign.amount_collected.get();
+        }
+        let amount_withdrawn = campaign.amount_withdrawn.get();
+        let held = campaign.amount_collected.get() - amount_withdrawn;
+        amount_withdrawn - campaign.fees_charged.get() + split_fee(held, fee_bps).0
+    }
+
+    /// Reverts unless the campaign has milestones, is not cancelled, none of its milestones
+    /// has been rejected and `milestone_index` has not been released yet.
+    fn check_milestone_pending(
+        campaign: &CampaignStorage,
+        campaign_id: U256,
+        milestone_index: U256,
+    ) -> Result<(), Vec<u8>> {
+        if campaign.milestones.is_empty() {
+            return Err(NoMilestones { campaignId: campaign_id }.abi_encode());
+        }
+        if campaign.cancelled.get() {
+            return Err(CampaignIsCancelled { campaignId: campaign_id }.abi_encode());
+        }
+        if campaign.milestone_failed.get() {
+            return Err(MilestoneVoteFailed { campaignId: campaign_id }.abi_encode());
+        }
+        if milestone_index >= U256::from(campaign.milestones.len()) {
+            return Err(AllMilestonesReleased { campaignId: campaign_id }.abi_encode());
+        }
+        Ok(())
+    }
+
+    /// Time by which the owner has to propose `milestone_index`: `MILESTONE_PROPOSAL_PERIOD` after
+    /// the campaign deadline for the first milestone, or after the previous milestone's vote closed.
+    fn proposal_deadline(campaign: &CampaignStorage, milestone_index: U256) -> U256 {
+        let start = if milestone_index == U256::from(0) {
+            campaign.deadline.get()
+        } else {
+            campaign
+                .milestones
+                .get((milestone_index - U256::from(1)).to::<usize>())
+                .map(|previous| previous.voting_deadline.get())
+                .unwrap_or_default()
+        };
+        start + U256::from(MILESTONE_PROPOSAL_PERIOD)
+    }
+
+    /// Returns the campaign's status, resolving an `Active` campaign whose deadline has passed
+    /// to `Successful` or `Failed`. Campaigns created before statuses were stored read as
+    /// `Draft` and are treated the same way as `Active` ones.
+    fn current_status(campaign: &CampaignStorage) -> Result<CampaignStatus, Vec<u8>> {
+        let stored = CampaignStatus::try_from(campaign.status.get().to::<u8>())?;
+        if stored != CampaignStatus::Active && stored != CampaignStatus::Draft {
+            return Ok(stored);
+        }
+        if campaign.cancelled.get() {
+            return Ok(CampaignStatus::Cancelled);
+        }
+        if campaign.milestone_failed.get() {
+            return Ok(CampaignStatus::Failed);
+        }
+
+        let current_time = U256::from(block::timestamp());
+        if campaign.deadline.get() > current_time {
+            return Ok(CampaignStatus::Active);
+        }
+
+        let funding_model = FundingModel::try_from(campaign.funding_model.get().to::<u8>())?;
+        if funding_model == FundingModel::KeepItAll
+            || campaign.amount_collected.get() >= campaign.target.get()
+        {
+            Ok(CampaignStatus::Successful)
+        } else {
+            Ok(CampaignStatus::Failed)
+        }
+    }
+
+    /// Writes the status returned by `current_status` to storage so that later transitions
+    /// start from it.
+    ///
+    /// Campaigns created before statuses were stored forwarded every donation to their owner as
+    /// it came in, so the first time one is settled everything it collected is recorded as
+    /// withdrawn and its deadline becomes the original one that extensions are capped against.
+    fn settle_status(campaign: &mut CampaignStorage, campaign_id: U256) -> Result<CampaignStatus, Vec<u8>> {
+        let previous = campaign.status.get().to::<u8>();
+        if previous == CampaignStatus::Draft as u8 {
+            let amount_collected = campaign.amount_collected.get();
+            campaign.amount_withdrawn.set(amount_collected);
+            let deadline = campaign.deadline.get();
+            campaign.original_deadline.set(deadline);
+        }
+        let status = Self::current_status(campaign)?;
+        if previous != status as u8 {
+            campaign.status.set(U8::from(status as u8));
+
+            // Emit CampaignStatusChanged event
+            evm::log(CampaignStatusChanged {
+                campaignId: campaign_id,
+                previousStatus: previous,
+                newStatus: status as u8,
+            });
+        }
+        Ok(status)
+    }
+
+    /// Moves the campaign to `status`, reverting if the stored status cannot transition to it.
+    fn set_status(campaign: &mut CampaignStorage, campaign_id: U256, status: CampaignStatus) -> Result<(), Vec<u8>> {
+        let previous = CampaignStatus::try_from(campaign.status.get().to::<u8>())?;
+        if !previous.can_become(status) {
+            return Err(InvalidStatusTransition {
+                previousStatus: previous as u8,
+                newStatus: status as u8,
+            }
+            .abi_encode());
+        }
+        campaign.status.set(U8::from(status as u8));
+
+        // Emit CampaignStatusChanged event
+        evm::log(CampaignStatusChanged {
+            campaignId: campaign_id,
+            previousStatus: previous as u8,
+            newStatus: status as u8,
+        });
+        Ok(())
+    }
+}
+
+/// Splits `amount` into the owner's net share and the platform fee. The fee is rounded down and
+/// the net share is whatever remains, so the two always add up to `amount`.
+fn split_fee(amount: U256, fee_bps: U256) -> (U256, U256) {
+    let fee = amount * fee_bps / U256::from(BPS_DENOMINATOR);
+    (amount - fee, fee)
+}
+
+/// Indices of the page starting at `offset` with at most `limit` (capped at `MAX_PAGE_SIZE`)
+/// entries out of `total`. Offsets past the end yield an empty page.
+fn page_range(offset: U256, limit: U256, total: U256) -> core::ops::Range<usize> {
+    let start = offset.min(total);
+    let end = start + limit.min(U256::from(MAX_PAGE_SIZE)).min(total - start);
+    start.to::<usize>()..end.to::<usize>()
+}
+
+/// Checks that `leaf` is part of the Merkle tree with the given `root`. Pairs of nodes are
+/// hashed in sorted order, matching OpenZeppelin's `MerkleProof` and `merkletreejs` with
+/// `sortPairs`.
+fn verify_merkle_proof(proof: &[FixedBytes<32>], root: FixedBytes<32>, leaf: FixedBytes<32>) -> bool {
+    let mut computed = leaf;
+    for node in proof {
+        let (first, second) = if computed <= *node { (computed, *node) } else { (*node, computed) };
+        let mut pair = [0u8; 64];
+        pair[..32].copy_from_slice(first.as_slice());
+        pair[32..].copy_from_slice(second.as_slice());
+        computed = crypto::keccak(pair);
     }
-}
\ No newline at end of file
+    computed == root
+}
+
+/// Hashes the editable metadata of a campaign as `keccak256(abi.encode(title, description, image))`.
+fn content_hash(title: String, description: String, image: String) -> FixedBytes<32> {
+    crypto::keccak((title, description, image).abi_encode_params())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,929 @@
+// Unit tests run natively against a small in-memory stand-in for the Stylus host. Storage is kept
+// per thread so tests running in parallel stay isolated. The SDK caches the sender, call value and
+// block timestamp for the whole process, so every test runs as `SENDER` at `NOW` and every
+// payable call carries `MSG_VALUE`.
+
+use super::*;
+use alloy_primitives::{address, b256, B256};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use stylus_sdk::storage::StorageType;
+
+const SENDER: Address = address!("1111111111111111111111111111111111111111");
+const OTHER: Address = address!("2222222222222222222222222222222222222222");
+const CONTRACT: Address = address!("3333333333333333333333333333333333333333");
+const TOKEN: Address = address!("4444444444444444444444444444444444444444");
+const NOW: u64 = 1_700_000_000;
+const MSG_VALUE: u64 = 1_000;
+
+mod host {
+    use super::*;
+
+    type Receiver = Box<dyn FnMut()>;
+
+    thread_local! {
+        static STORAGE: RefCell<HashMap<B256, B256>> = RefCell::new(HashMap::new());
+        static TRANSFERS: RefCell<Vec<(Address, U256)>> = const { RefCell::new(Vec::new()) };
+        static RECEIVER: RefCell<Option<Receiver>> = const { RefCell::new(None) };
+        static TOKEN_CALLS: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+        static TOKEN_RESPONSE: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+        static RETURN_DATA: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Forgets the storage, transfers and receiver of the current test.
+    pub fn reset() {
+        STORAGE.with(|storage| storage.borrow_mut().clear());
+        TRANSFERS.with(|transfers| transfers.borrow_mut().clear());
+        RECEIVER.with(|receiver| receiver.borrow_mut().take());
+        TOKEN_CALLS.with(|calls| calls.borrow_mut().clear());
+        TOKEN_RESPONSE.with(|response| response.borrow_mut().clear());
+    }
+
+    /// Makes every call to `TOKEN` return `data`. Tokens return nothing until this is called.
+    pub fn token_returns(data: Vec<u8>) {
+        TOKEN_RESPONSE.with(|response| *response.borrow_mut() = data);
+    }
+
+    /// Calldata of every call the contract made to `TOKEN`, in order.
+    pub fn token_calls() -> Vec<Vec<u8>> {
+        TOKEN_CALLS.with(|calls| calls.borrow().clone())
+    }
+
+    /// Runs `receive` whenever the contract sends ETH, like the fallback of a receiving contract.
+    pub fn on_receive(receive: impl FnMut() + 'static) {
+        RECEIVER.with(|receiver| *receiver.borrow_mut() = Some(Box::new(receive)));
+    }
+
+    /// Every ETH transfer made by the contract, in order.
+    pub fn transfers() -> Vec<(Address, U256)> {
+        TRANSFERS.with(|transfers| transfers.borrow().clone())
+    }
+
+    /// Every slot written since the last `reset`, in ascending order.
+    pub fn written_slots() -> Vec<U256> {
+        let mut slots: Vec<U256> =
+            STORAGE.with(|storage| storage.borrow().keys().map(|key| U256::from_be_bytes(key.0)).collect());
+        slots.sort();
+        slots
+    }
+
+    /// Runs `call` as a transaction: if it reverts, its storage writes and transfers are undone
+    /// like the EVM would. Storage fields cache what they read, so `call` should run against a
+    /// fresh `contract()` that is dropped with it.
+    pub fn transact<T>(call: impl FnOnce() -> Result<T, Vec<u8>>) -> Result<T, Vec<u8>> {
+        let storage = STORAGE.with(|storage| storage.borrow().clone());
+        let transfers = transfers();
+        let result = call();
+        if result.is_err() {
+            STORAGE.with(|current| *current.borrow_mut() = storage);
+            TRANSFERS.with(|current| *current.borrow_mut() = transfers);
+        }
+        result
+    }
+
+    pub fn word(slot: U256) -> B256 {
+        STORAGE.with(|storage| storage.borrow().get(&B256::from(slot)).copied().unwrap_or_default())
+    }
+
+    unsafe fn read_word(ptr: *const u8) -> B256 {
+        B256::from_slice(std::slice::from_raw_parts(ptr, 32))
+    }
+
+    unsafe fn write_bytes(dest: *mut u8, bytes: &[u8]) {
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), dest, bytes.len());
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn storage_load_bytes32(key: *const u8, dest: *mut u8) {
+        write_bytes(dest, word(U256::from_be_bytes(read_word(key).0)).as_slice());
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn storage_cache_bytes32(key: *const u8, value: *const u8) {
+        let (key, value) = (read_word(key), read_word(value));
+        STORAGE.with(|storage| storage.borrow_mut().insert(key, value));
+    }
+
+    #[no_mangle]
+    pub extern "C" fn storage_flush_cache(_clear: bool) {}
+
+    #[no_mangle]
+    pub unsafe extern "C" fn msg_sender(sender: *mut u8) {
+        write_bytes(sender, SENDER.as_slice());
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn msg_value(value: *mut u8) {
+        write_bytes(value, B256::from(U256::from(MSG_VALUE)).as_slice());
+    }
+
+    #[no_mangle]
+    pub extern "C" fn block_timestamp() -> u64 {
+        NOW
+    }
+
+    #[no_mangle]
+    pub extern "C" fn emit_log(_data: *const u8, _len: usize, _topics: usize) {}
+
+    #[no_mangle]
+    pub unsafe extern "C" fn contract_address(address: *mut u8) {
+        write_bytes(address, CONTRACT.as_slice());
+    }
+
+    /// Only `TOKEN` has code.
+    #[no_mangle]
+    pub unsafe extern "C" fn account_codehash(address: *const u8, dest: *mut u8) {
+        let hash = if Address::from_slice(std::slice::from_raw_parts(address, 20)) == TOKEN {
+            B256::repeat_byte(0xc0)
+        } else {
+            B256::ZERO
+        };
+        write_bytes(dest, hash.as_slice());
+    }
+
+    /// Calls with calldata go to a token: they are recorded and answer with the data set by
+    /// `token_returns`. Calls without calldata are ETH transfers: they are recorded and run the
+    /// receiver, which always accepts the ETH. The receiver is taken out while it runs so that it
+    /// can make calls that send ETH again.
+    #[no_mangle]
+    pub unsafe extern "C" fn call_contract(
+        contract: *const u8,
+        calldata: *const u8,
+        calldata_len: usize,
+        value: *const u8,
+        _gas: u64,
+        return_data_len: *mut usize,
+    ) -> u8 {
+        let to = Address::from_slice(std::slice::from_raw_parts(contract, 20));
+        if calldata_len > 0 {
+            let calldata = std::slice::from_raw_parts(calldata, calldata_len).to_vec();
+            TOKEN_CALLS.with(|calls| calls.borrow_mut().push(calldata));
+            let response = TOKEN_RESPONSE.with(|response| response.borrow().clone());
+            *return_data_len = response.len();
+            RETURN_DATA.with(|data| *data.borrow_mut() = response);
+            return 0;
+        }
+
+        let amount = U256::from_be_bytes(read_word(value).0);
+        TRANSFERS.with(|transfers| transfers.borrow_mut().push((to, amount)));
+
+        if let Some(mut receive) = RECEIVER.with(|receiver| receiver.borrow_mut().take()) {
+            receive();
+            RECEIVER.with(|receiver| {
+                receiver.borrow_mut().get_or_insert(receive);
+            });
+        }
+
+        *return_data_len = 0;
+        RETURN_DATA.with(|data| data.borrow_mut().clear());
+        0
+    }
+
+    #[no_mangle]
+    pub extern "C" fn return_data_size() -> usize {
+        RETURN_DATA.with(|data| data.borrow().len())
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn read_return_data(dest: *mut u8, offset: usize, size: usize) -> usize {
+        RETURN_DATA.with(|data| {
+            let data = data.borrow();
+            let start = offset.min(data.len());
+            let end = start + size.min(data.len() - start);
+            write_bytes(dest, &data[start..end]);
+            end - start
+        })
+    }
+
+    /// Reverts with no data, since the contract makes no delegate calls.
+    #[no_mangle]
+    pub unsafe extern "C" fn delegate_call_contract(
+        _contract: *const u8,
+        _calldata: *const u8,
+        _calldata_len: usize,
+        _gas: u64,
+        return_data_len: *mut usize,
+    ) -> u8 {
+        *return_data_len = 0;
+        1
+    }
+
+    /// Reverts with no data, so view calls to tokens fail the way they would against an account
+    /// without code.
+    #[no_mangle]
+    pub unsafe extern "C" fn static_call_contract(
+        _contract: *const u8,
+        _calldata: *const u8,
+        _calldata_len: usize,
+        _gas: u64,
+        return_data_len: *mut usize,
+    ) -> u8 {
+        *return_data_len = 0;
+        1
+    }
+}
+
+fn contract() -> CrowdFunding {
+    unsafe { CrowdFunding::new(U256::ZERO, 0) }
+}
+
+/// Settings of a campaign created by `create_campaign_with`. The default is a public keep-it-all
+/// ETH campaign with a target of ten donations and no limits or milestones.
+struct CampaignParams {
+    funding_model: FundingModel,
+    retractable: bool,
+    token: Address,
+    target: U256,
+    min_donation: U256,
+    max_donation: U256,
+    max_per_donor: U256,
+    hard_cap: U256,
+    allowlist_root: FixedBytes<32>,
+    milestone_amounts: Vec<U256>,
+    milestone_hashes: Vec<FixedBytes<32>>,
+}
+
+impl Default for CampaignParams {
+    fn default() -> Self {
+        Self {
+            funding_model: FundingModel::KeepItAll,
+            retractable: false,
+            token: Address::ZERO,
+            target: U256::from(10 * MSG_VALUE),
+            min_donation: U256::ZERO,
+            max_donation: U256::ZERO,
+            max_per_donor: U256::ZERO,
+            hard_cap: U256::ZERO,
+            allowlist_root: FixedBytes::ZERO,
+            milestone_amounts: vec![],
+            milestone_hashes: vec![],
+        }
+    }
+}
+
+/// Creates a campaign owned by `SENDER` that ends at `NOW + 1_000`.
+fn create_campaign_with(c: &mut CrowdFunding, params: CampaignParams) -> Result<U256, Vec<u8>> {
+    c.create_campaign(
+        SENDER,
+        "title".into(),
+        "description".into(),
+        params.target,
+        U256::from(NOW + 1_000),
+        "image".into(),
+        params.funding_model as u8,
+        params.retractable,
+        params.token,
+        params.min_donation,
+        params.max_donation,
+        params.max_per_donor,
+        params.hard_cap,
+        params.allowlist_root,
+        params.milestone_amounts,
+        params.milestone_hashes,
+    )
+}
+
+/// Creates an ETH campaign owned by `SENDER` with a target of ten donations.
+fn create_campaign(c: &mut CrowdFunding, funding_model: FundingModel, retractable: bool) -> U256 {
+    create_campaign_with(c, CampaignParams { funding_model, retractable, ..Default::default() }).unwrap()
+}
+
+/// Result of a call made from inside an ETH transfer, once the transfer has happened.
+type Reentry = Rc<RefCell<Option<Result<(), Vec<u8>>>>>;
+
+/// Makes the next ETH transfer call `reenter` from the receiving end.
+fn reenter_on_receive(mut reenter: impl FnMut(&mut CrowdFunding) -> Result<(), Vec<u8>> + 'static) -> Reentry {
+    let result = Rc::new(RefCell::new(None));
+    let slot = result.clone();
+    host::on_receive(move || {
+        slot.borrow_mut().get_or_insert_with(|| reenter(&mut contract()));
+    });
+    result
+}
+
+/// Runs `write` against fresh storage and returns the slots it touched.
+fn slots_written_by(write: impl FnOnce(&mut CrowdFunding)) -> Vec<U256> {
+    host::reset();
+    write(&mut contract());
+    host::written_slots()
+}
+
+/// Slot of `key` in a mapping rooted at `root`.
+fn mapping_slot(key: B256, root: U256) -> U256 {
+    U256::from_be_bytes(crypto::keccak([key.0, B256::from(root).0].concat()).0)
+}
+
+fn address_key(account: Address) -> B256 {
+    B256::left_padding_from(account.as_slice())
+}
+
+/// First element slot of a dynamic array whose length lives at `slot`.
+fn array_slot(slot: U256) -> U256 {
+    U256::from_be_bytes(crypto::keccak(B256::from(slot)).0)
+}
+
+fn sorted(mut slots: Vec<U256>) -> Vec<U256> {
+    slots.sort();
+    slots
+}
+
+#[test]
+fn crowd_funding_slots_are_stable() {
+    let slot = U256::from;
+
+    assert_eq!(slots_written_by(|c| c.no_of_campaigns.set(slot(1))), vec![slot(0)]);
+    assert_eq!(
+        slots_written_by(|c| c.campaigns.setter(U256::ZERO).target.set(slot(1))),
+        vec![mapping_slot(B256::ZERO, slot(1)) + slot(3)],
+    );
+
+    // `locked` and `admin` share slot 2: the flag in the lowest byte, the address above it.
+    assert_eq!(slots_written_by(|c| c.locked.set(true)), vec![slot(2)]);
+    assert_eq!(host::word(slot(2))[31], 1);
+    assert_eq!(slots_written_by(|c| c.admin.set(OTHER)), vec![slot(2)]);
+    assert_eq!(&host::word(slot(2))[11..31], OTHER.as_slice());
+
+    assert_eq!(slots_written_by(|c| c.treasury.set(OTHER)), vec![slot(3)]);
+    assert_eq!(slots_written_by(|c| c.fee_bps.set(slot(1))), vec![slot(4)]);
+    assert_eq!(
+        slots_written_by(|c| c.roles.setter(ADMIN_ROLE).insert(OTHER, true)),
+        vec![mapping_slot(address_key(OTHER), mapping_slot(ADMIN_ROLE, slot(5)))],
+    );
+    assert_eq!(slots_written_by(|c| c.paused.set(true)), vec![slot(6)]);
+    assert_eq!(slots_written_by(|c| c.storage_version.set(slot(1))), vec![slot(7)]);
+
+    let by_owner = mapping_slot(address_key(OTHER), slot(8));
+    assert_eq!(
+        slots_written_by(|c| c.campaigns_by_owner.setter(OTHER).push(slot(1))),
+        sorted(vec![by_owner, array_slot(by_owner)]),
+    );
+    let by_donor = mapping_slot(address_key(OTHER), slot(9));
+    assert_eq!(
+        slots_written_by(|c| c.campaigns_by_donor.setter(OTHER).push(slot(1))),
+        sorted(vec![by_donor, array_slot(by_donor)]),
+    );
+}
+
+#[test]
+fn campaign_storage_slots_are_stable() {
+    let base = mapping_slot(B256::ZERO, U256::from(1));
+    let slot = |index: u64| base + U256::from(index);
+    let campaign_slots = |write: &dyn Fn(&mut CampaignStorage)| {
+        slots_written_by(|c| write(&mut c.campaigns.setter(U256::ZERO)))
+    };
+    let one = U256::from(1);
+
+    // The original layout.
+    assert_eq!(campaign_slots(&|c| c.owner.set(OTHER)), vec![slot(0)]);
+    assert_eq!(campaign_slots(&|c| c.title.set_str("t")), vec![slot(1)]);
+    assert_eq!(campaign_slots(&|c| c.description.set_str("d")), vec![slot(2)]);
+    assert_eq!(campaign_slots(&|c| c.target.set(one)), vec![slot(3)]);
+    assert_eq!(campaign_slots(&|c| c.deadline.set(one)), vec![slot(4)]);
+    assert_eq!(campaign_slots(&|c| c.amount_collected.set(one)), vec![slot(5)]);
+    assert_eq!(campaign_slots(&|c| c.image.set_str("i")), vec![slot(6)]);
+    assert_eq!(
+        campaign_slots(&|c| c.donators.push(OTHER)),
+        sorted(vec![slot(7), array_slot(slot(7))]),
+    );
+    assert_eq!(
+        campaign_slots(&|c| c.donations.push(one)),
+        sorted(vec![slot(8), array_slot(slot(8))]),
+    );
+
+    // Appended fields, in the order they were added.
+    assert_eq!(campaign_slots(&|c| c.funding_model.set(U8::from(1))), vec![slot(9)]);
+    assert_eq!(campaign_slots(&|c| c.amount_withdrawn.set(one)), vec![slot(10)]);
+    assert_eq!(
+        campaign_slots(&|c| c.refunded.insert(OTHER, true)),
+        vec![mapping_slot(address_key(OTHER), slot(11))],
+    );
+    assert_eq!(
+        campaign_slots(&|c| c.milestones.grow().amount.set(one)),
+        sorted(vec![slot(12), array_slot(slot(12))]),
+    );
+    assert_eq!(campaign_slots(&|c| c.current_milestone.set(one)), vec![slot(13)]);
+    assert_eq!(campaign_slots(&|c| c.milestone_failed.set(true)), vec![slot(14)]);
+    assert_eq!(host::word(slot(14))[31], 1);
+    assert_eq!(campaign_slots(&|c| c.cancelled.set(true)), vec![slot(14)]);
+    assert_eq!(host::word(slot(14))[30], 1);
+    assert_eq!(campaign_slots(&|c| c.original_deadline.set(one)), vec![slot(15)]);
+    assert_eq!(campaign_slots(&|c| c.revision.set(one)), vec![slot(16)]);
+    assert_eq!(
+        campaign_slots(&|c| c.contributions.insert(OTHER, one)),
+        vec![mapping_slot(address_key(OTHER), slot(17))],
+    );
+    assert_eq!(campaign_slots(&|c| c.donor_count.set(one)), vec![slot(18)]);
+    assert_eq!(campaign_slots(&|c| c.retractable.set(true)), vec![slot(19)]);
+    assert_eq!(host::word(slot(19))[31], 1);
+    assert_eq!(campaign_slots(&|c| c.token.set(OTHER)), vec![slot(19)]);
+    assert_eq!(&host::word(slot(19))[11..31], OTHER.as_slice());
+    assert_eq!(campaign_slots(&|c| c.min_donation.set(one)), vec![slot(20)]);
+    assert_eq!(campaign_slots(&|c| c.max_donation.set(one)), vec![slot(21)]);
+    assert_eq!(campaign_slots(&|c| c.max_per_donor.set(one)), vec![slot(22)]);
+    assert_eq!(campaign_slots(&|c| c.hard_cap.set(one)), vec![slot(23)]);
+    assert_eq!(campaign_slots(&|c| c.allowlist_root.set(B256::repeat_byte(1))), vec![slot(24)]);
+    assert_eq!(campaign_slots(&|c| c.status.set(U8::from(1))), vec![slot(25)]);
+    assert_eq!(
+        campaign_slots(&|c| c.indexed_donors.insert(OTHER, true)),
+        vec![mapping_slot(address_key(OTHER), slot(26))],
+    );
+    assert_eq!(campaign_slots(&|c| c.fees_charged.set(one)), vec![slot(27)]);
+    assert_eq!(campaign_slots(&|c| c.payout_count.set(one)), vec![slot(28)]);
+    assert_eq!(
+        campaign_slots(&|c| c.held_contributions.insert(OTHER, one)),
+        vec![mapping_slot(address_key(OTHER), slot(29))],
+    );
+    assert_eq!(
+        campaign_slots(&|c| c.held_since_payout.insert(OTHER, one)),
+        vec![mapping_slot(address_key(OTHER), slot(30))],
+    );
+}
+
+#[test]
+fn content_hash_matches_solidity_abi_encode() {
+    // keccak256(abi.encode("title", "description", "image")), which encodes to 288 bytes.
+    assert_eq!(
+        content_hash("title".into(), "description".into(), "image".into()),
+        b256!("a89ee5ef6cc32b8af5f225b874460e6e27f1adde0a097a508ade53182fa78dbb"),
+    );
+}
+
+#[test]
+fn withdraw_cannot_be_reentered() {
+    host::reset();
+    let mut c = contract();
+    let campaign_id = create_campaign(&mut c, FundingModel::KeepItAll, false);
+    c.donate_to_campaign(campaign_id, vec![]).unwrap();
+
+    let reentry = reenter_on_receive(move |c| c.withdraw(campaign_id));
+    c.withdraw(campaign_id).unwrap();
+
+    assert_eq!(*reentry.borrow(), Some(Err(ReentrantCall {}.abi_encode())));
+    assert_eq!(host::transfers(), vec![(SENDER, U256::from(MSG_VALUE))]);
+}
+
+#[test]
+fn claim_refund_cannot_be_reentered() {
+    host::reset();
+    let mut c = contract();
+    let campaign_id = create_campaign(&mut c, FundingModel::AllOrNothing, false);
+    c.donate_to_campaign(campaign_id, vec![]).unwrap();
+    c.cancel_campaign(campaign_id).unwrap();
+
+    let reentry = reenter_on_receive(move |c| c.claim_refund(campaign_id));
+    c.claim_refund(campaign_id).unwrap();
+
+    assert_eq!(*reentry.borrow(), Some(Err(ReentrantCall {}.abi_encode())));
+    assert_eq!(host::transfers(), vec![(SENDER, U256::from(MSG_VALUE))]);
+    assert_eq!(
+        c.claim_refund(campaign_id),
+        Err(AlreadyRefunded { campaignId: campaign_id, donor: SENDER }.abi_encode()),
+    );
+}
+
+#[test]
+fn retract_donation_cannot_be_reentered() {
+    host::reset();
+    let mut c = contract();
+    let campaign_id = create_campaign(&mut c, FundingModel::AllOrNothing, true);
+    c.donate_to_campaign(campaign_id, vec![]).unwrap();
+
+    let half = U256::from(MSG_VALUE / 2);
+    let reentry = reenter_on_receive(move |c| c.retract_donation(campaign_id, half));
+    c.retract_donation(campaign_id, half).unwrap();
+
+    assert_eq!(*reentry.borrow(), Some(Err(ReentrantCall {}.abi_encode())));
+    assert_eq!(host::transfers(), vec![(SENDER, half)]);
+    assert_eq!(c.get_contribution(campaign_id, SENDER), half);
+}
+
+#[test]
+fn retract_donation_reduces_the_donation_history() {
+    host::reset();
+    let mut c = contract();
+    let campaign_id = create_campaign(&mut c, FundingModel::AllOrNothing, true);
+    c.donate_to_campaign(campaign_id, vec![]).unwrap();
+    c.donate_to_campaign(campaign_id, vec![]).unwrap();
+
+    c.retract_donation(campaign_id, U256::from(MSG_VALUE * 3 / 2)).unwrap();
+
+    let half = U256::from(MSG_VALUE / 2);
+    assert_eq!(c.get_donators(campaign_id), (vec![SENDER, SENDER], vec![half, U256::ZERO]));
+    assert_eq!(c.get_campaign(campaign_id).unwrap().5, half);
+}
+
+/// Moves the deadline of a campaign to `NOW`, ending it.
+fn end_campaign(c: &mut CrowdFunding, campaign_id: U256) {
+    c.campaigns.setter(campaign_id).deadline.set(U256::from(NOW));
+}
+
+#[test]
+fn failed_campaign_can_be_cancelled() {
+    host::reset();
+    let mut c = contract();
+    let campaign_id = create_campaign(&mut c, FundingModel::AllOrNothing, false);
+    c.donate_to_campaign(campaign_id, vec![]).unwrap();
+    end_campaign(&mut c, campaign_id);
+    assert_eq!(c.get_campaign_status(campaign_id), Ok(CampaignStatus::Failed as u8));
+
+    c.cancel_campaign(campaign_id).unwrap();
+
+    assert_eq!(c.get_campaign_status(campaign_id), Ok(CampaignStatus::Cancelled as u8));
+}
+
+#[test]
+fn keep_it_all_campaign_emptied_early_ends_withdrawn() {
+    host::reset();
+    let mut c = contract();
+    let campaign_id = create_campaign(&mut c, FundingModel::KeepItAll, false);
+    c.donate_to_campaign(campaign_id, vec![]).unwrap();
+    c.withdraw(campaign_id).unwrap();
+    assert_eq!(c.get_campaign_status(campaign_id), Ok(CampaignStatus::Active as u8));
+
+    end_campaign(&mut c, campaign_id);
+    assert_eq!(c.get_campaign_status(campaign_id), Ok(CampaignStatus::Successful as u8));
+    c.withdraw(campaign_id).unwrap();
+
+    assert_eq!(c.get_campaign_status(campaign_id), Ok(CampaignStatus::Withdrawn as u8));
+    assert_eq!(host::transfers(), vec![(SENDER, U256::from(MSG_VALUE))]);
+    assert_eq!(c.withdraw(campaign_id), Err(NothingToWithdraw { campaignId: campaign_id }.abi_encode()));
+}
+
+#[test]
+fn net_amount_keeps_the_fee_charged_on_earlier_payouts() {
+    host::reset();
+    let mut c = contract();
+    c.initialize(SENDER).unwrap();
+    c.set_treasury(OTHER).unwrap();
+    c.set_fee(U256::from(1_000)).unwrap();
+    let campaign_id = create_campaign(&mut c, FundingModel::KeepItAll, false);
+    c.donate_to_campaign(campaign_id, vec![]).unwrap();
+    c.withdraw(campaign_id).unwrap();
+    c.donate_to_campaign(campaign_id, vec![]).unwrap();
+
+    c.set_fee(U256::from(500)).unwrap();
+
+    // 900 paid out at 10%, plus 950 for the 1000 still held at 5%.
+    let (.., gross_amounts, net_amounts, _) = c.get_campaigns_page(U256::ZERO, U256::from(1));
+    assert_eq!(gross_amounts, vec![U256::from(2 * MSG_VALUE)]);
+    assert_eq!(net_amounts, vec![U256::from(1_850)]);
+}
+
+/// Writes a campaign as the original contract stored it, before any field was appended. That
+/// contract forwarded each donation to the owner, so nothing of `amount_collected` is held.
+fn seed_legacy_campaign(c: &mut CrowdFunding) -> U256 {
+    let campaign_id = c.no_of_campaigns.get();
+    let mut campaign = c.campaigns.setter(campaign_id);
+    campaign.owner.set(SENDER);
+    campaign.title.set_str("title");
+    campaign.description.set_str("description");
+    campaign.target.set(U256::from(10 * MSG_VALUE));
+    campaign.deadline.set(U256::from(NOW + 1_000));
+    campaign.amount_collected.set(U256::from(MSG_VALUE));
+    campaign.image.set_str("image");
+    campaign.donators.push(OTHER);
+    campaign.donations.push(U256::from(MSG_VALUE));
+    c.no_of_campaigns.set(campaign_id + U256::from(1));
+    campaign_id
+}
+
+#[test]
+fn legacy_campaign_cannot_withdraw_what_was_already_forwarded() {
+    host::reset();
+    let mut c = contract();
+    let legacy_id = seed_legacy_campaign(&mut c);
+    let escrowed_id = create_campaign(&mut c, FundingModel::AllOrNothing, false);
+    c.donate_to_campaign(escrowed_id, vec![]).unwrap();
+
+    assert_eq!(
+        host::transact(|| contract().withdraw(legacy_id)),
+        Err(NothingToWithdraw { campaignId: legacy_id }.abi_encode()),
+    );
+    assert_eq!(host::transfers(), vec![]);
+
+    // Donations made after the upgrade are held and paid out as usual.
+    c.donate_to_campaign(legacy_id, vec![]).unwrap();
+    c.withdraw(legacy_id).unwrap();
+    assert_eq!(host::transfers(), vec![(SENDER, U256::from(MSG_VALUE))]);
+}
+
+#[test]
+fn legacy_campaign_deadline_can_be_extended() {
+    host::reset();
+    let mut c = contract();
+    let campaign_id = seed_legacy_campaign(&mut c);
+
+    let new_deadline = U256::from(NOW + 1_000 + MAX_DEADLINE_EXTENSION);
+    c.extend_deadline(campaign_id, new_deadline).unwrap();
+
+    assert_eq!(c.get_campaign(campaign_id).unwrap().4, new_deadline);
+}
+
+#[test]
+fn cancelled_keep_it_all_campaign_refunds_only_donations_since_the_last_payout() {
+    host::reset();
+    let mut c = contract();
+    let campaign_id = create_campaign(&mut c, FundingModel::KeepItAll, false);
+
+    // Every call runs as `SENDER`, so the donation from `OTHER` that is paid out is seeded.
+    let mut campaign = c.campaigns.setter(campaign_id);
+    campaign.amount_collected.set(U256::from(MSG_VALUE));
+    campaign.contributions.insert(OTHER, U256::from(MSG_VALUE));
+    c.withdraw(campaign_id).unwrap();
+
+    c.donate_to_campaign(campaign_id, vec![]).unwrap();
+    c.cancel_campaign(campaign_id).unwrap();
+    c.claim_refund(campaign_id).unwrap();
+
+    assert_eq!(
+        host::transfers(),
+        vec![(SENDER, U256::from(MSG_VALUE)), (SENDER, U256::from(MSG_VALUE))],
+    );
+}
+
+/// Creates an all-or-nothing campaign owned by `SENDER` with two milestones, funds it to its
+/// target of ten donations and ends it.
+fn funded_milestone_campaign(c: &mut CrowdFunding) -> U256 {
+    let params = CampaignParams {
+        funding_model: FundingModel::AllOrNothing,
+        milestone_amounts: vec![U256::from(4 * MSG_VALUE), U256::from(6 * MSG_VALUE)],
+        milestone_hashes: vec![B256::repeat_byte(1), B256::repeat_byte(2)],
+        ..Default::default()
+    };
+    let campaign_id = create_campaign_with(c, params).unwrap();
+    for _ in 0..10 {
+        c.donate_to_campaign(campaign_id, vec![]).unwrap();
+    }
+    end_campaign(c, campaign_id);
+    campaign_id
+}
+
+/// Closes the vote on the milestone at `index`.
+fn end_vote(c: &mut CrowdFunding, campaign_id: U256, index: usize) {
+    c.campaigns.setter(campaign_id).milestones.setter(index).unwrap().voting_deadline.set(U256::from(NOW));
+}
+
+#[test]
+fn approved_milestones_are_released_in_turn() {
+    host::reset();
+    let mut c = contract();
+    let campaign_id = funded_milestone_campaign(&mut c);
+
+    for (index, amount) in [(0, 4 * MSG_VALUE), (1, 6 * MSG_VALUE)] {
+        c.propose_milestone(campaign_id).unwrap();
+        c.vote_on_milestone(campaign_id, true).unwrap();
+        assert_eq!(
+            host::transact(|| contract().release_milestone(campaign_id)),
+            Err(VotingStillOpen {
+                votingDeadline: U256::from(NOW + MILESTONE_VOTING_PERIOD),
+                currentTime: U256::from(NOW),
+            }
+            .abi_encode()),
+        );
+        end_vote(&mut c, campaign_id, index);
+        c.release_milestone(campaign_id).unwrap();
+        assert_eq!(host::transfers().last(), Some(&(SENDER, U256::from(amount))));
+    }
+
+    assert_eq!(c.get_campaign_status(campaign_id), Ok(CampaignStatus::Withdrawn as u8));
+    assert_eq!(
+        host::transact(|| contract().propose_milestone(campaign_id)),
+        Err(AllMilestonesReleased { campaignId: campaign_id }.abi_encode()),
+    );
+}
+
+#[test]
+fn rejected_milestone_refunds_what_was_not_released() {
+    host::reset();
+    let mut c = contract();
+    let campaign_id = funded_milestone_campaign(&mut c);
+    c.propose_milestone(campaign_id).unwrap();
+    c.vote_on_milestone(campaign_id, true).unwrap();
+    end_vote(&mut c, campaign_id, 0);
+    c.release_milestone(campaign_id).unwrap();
+
+    c.propose_milestone(campaign_id).unwrap();
+    c.vote_on_milestone(campaign_id, false).unwrap();
+    end_vote(&mut c, campaign_id, 1);
+    c.release_milestone(campaign_id).unwrap();
+
+    assert_eq!(c.get_campaign_status(campaign_id), Ok(CampaignStatus::Failed as u8));
+    assert_eq!(
+        host::transact(|| contract().withdraw(campaign_id)),
+        Err(MilestonesPending { campaignId: campaign_id }.abi_encode()),
+    );
+    c.claim_refund(campaign_id).unwrap();
+    assert_eq!(
+        host::transfers(),
+        vec![(SENDER, U256::from(4 * MSG_VALUE)), (SENDER, U256::from(6 * MSG_VALUE))],
+    );
+}
+
+#[test]
+fn missed_milestone_proposal_makes_the_campaign_refundable() {
+    host::reset();
+    let mut c = contract();
+    let campaign_id = funded_milestone_campaign(&mut c);
+    let proposal_deadline = U256::from(NOW + MILESTONE_PROPOSAL_PERIOD);
+    assert_eq!(
+        host::transact(|| contract().fail_missed_milestone(campaign_id)),
+        Err(ProposalPeriodOpen { proposalDeadline: proposal_deadline, currentTime: U256::from(NOW) }.abi_encode()),
+    );
+
+    // Move the end of the campaign back so that the proposal period is over.
+    c.campaigns.setter(campaign_id).deadline.set(U256::from(NOW - MILESTONE_PROPOSAL_PERIOD));
+    assert_eq!(
+        host::transact(|| contract().propose_milestone(campaign_id)),
+        Err(ProposalPeriodOver { proposalDeadline: U256::from(NOW), currentTime: U256::from(NOW) }.abi_encode()),
+    );
+    c.fail_missed_milestone(campaign_id).unwrap();
+
+    assert_eq!(c.get_campaign_status(campaign_id), Ok(CampaignStatus::Failed as u8));
+    c.claim_refund(campaign_id).unwrap();
+    assert_eq!(host::transfers(), vec![(SENDER, U256::from(10 * MSG_VALUE))]);
+}
+
+/// Creates a keep-it-all campaign denominated in `token`.
+fn create_token_campaign(c: &mut CrowdFunding, token: Address) -> U256 {
+    create_campaign_with(c, CampaignParams { token, ..Default::default() }).unwrap()
+}
+
+#[test]
+fn token_transfers_without_a_return_value_succeed() {
+    host::reset();
+    let mut c = contract();
+    let campaign_id = create_token_campaign(&mut c, TOKEN);
+    let amount = U256::from(MSG_VALUE);
+
+    c.donate_token(campaign_id, TOKEN, amount, vec![]).unwrap();
+    c.withdraw(campaign_id).unwrap();
+
+    assert_eq!(
+        host::token_calls(),
+        vec![
+            transferFromCall { from: SENDER, to: CONTRACT, amount }.abi_encode(),
+            transferCall { to: SENDER, amount }.abi_encode(),
+        ],
+    );
+}
+
+#[test]
+fn token_transfers_returning_true_succeed() {
+    host::reset();
+    let mut c = contract();
+    let campaign_id = create_token_campaign(&mut c, TOKEN);
+    host::token_returns(true.abi_encode());
+
+    c.donate_token(campaign_id, TOKEN, U256::from(MSG_VALUE), vec![]).unwrap();
+
+    assert_eq!(c.get_contribution(campaign_id, SENDER), U256::from(MSG_VALUE));
+}
+
+#[test]
+fn token_transfers_returning_false_revert() {
+    host::reset();
+    let mut c = contract();
+    let campaign_id = create_token_campaign(&mut c, TOKEN);
+    host::token_returns(false.abi_encode());
+
+    assert_eq!(
+        c.donate_token(campaign_id, TOKEN, U256::from(MSG_VALUE), vec![]),
+        Err(TokenTransferFailed { token: TOKEN }.abi_encode()),
+    );
+}
+
+#[test]
+fn token_transfers_to_an_account_without_code_revert() {
+    host::reset();
+    let mut c = contract();
+    let campaign_id = create_token_campaign(&mut c, OTHER);
+
+    assert_eq!(
+        c.donate_token(campaign_id, OTHER, U256::from(MSG_VALUE), vec![]),
+        Err(TokenTransferFailed { token: OTHER }.abi_encode()),
+    );
+}
+
+// The tree `new MerkleTree(leaves, keccak256, { sortPairs: true })` from merkletreejs builds over
+// the keccak256 of the addresses 0x11…11, 0x22…22, 0x33…33 and 0x44…44, in that order.
+const ALLOWLIST_ROOT: B256 = b256!("8ea0e3a5b1bcc3d21d094be4a529068bb97ef23671d5a18bc24c5ae11cffdbf7");
+const OTHER_LEAF: B256 = b256!("2ab0a4443bbea3fbe4d0e1503d11ff1367842fb0c8b28a5c8550f27599a40751");
+const RIGHT_BRANCH: B256 = b256!("0aafebc39b02f78812dd98aa2d43138e57bf2e2129476469fcffb7c1d572f346");
+
+#[test]
+fn merkle_proofs_match_sorted_pair_trees() {
+    let sender_leaf = crypto::keccak(SENDER.as_slice());
+    let proof = [OTHER_LEAF, RIGHT_BRANCH];
+
+    assert!(verify_merkle_proof(&proof, ALLOWLIST_ROOT, sender_leaf));
+    assert!(!verify_merkle_proof(&proof, ALLOWLIST_ROOT, crypto::keccak(OTHER.as_slice())));
+    let mut tampered = RIGHT_BRANCH;
+    tampered.0[31] ^= 1;
+    assert!(!verify_merkle_proof(&[OTHER_LEAF, tampered], ALLOWLIST_ROOT, sender_leaf));
+    assert!(!verify_merkle_proof(&[], ALLOWLIST_ROOT, sender_leaf));
+}
+
+#[test]
+fn private_campaign_only_accepts_allowlisted_donors() {
+    host::reset();
+    let mut c = contract();
+    let params = CampaignParams { allowlist_root: ALLOWLIST_ROOT, ..Default::default() };
+    let campaign_id = create_campaign_with(&mut c, params).unwrap();
+
+    assert_eq!(
+        host::transact(|| contract().donate_to_campaign(campaign_id, vec![])),
+        Err(NotAllowlisted { campaignId: campaign_id, donor: SENDER }.abi_encode()),
+    );
+    c.donate_to_campaign(campaign_id, vec![OTHER_LEAF, RIGHT_BRANCH]).unwrap();
+
+    assert_eq!(c.get_contribution(campaign_id, SENDER), U256::from(MSG_VALUE));
+}
+
+#[test]
+fn donation_overshooting_the_hard_cap_is_clipped_and_closes_the_campaign() {
+    host::reset();
+    let mut c = contract();
+    let params = CampaignParams {
+        target: U256::from(MSG_VALUE),
+        hard_cap: U256::from(MSG_VALUE * 3 / 2),
+        ..Default::default()
+    };
+    let campaign_id = create_campaign_with(&mut c, params).unwrap();
+    c.donate_to_campaign(campaign_id, vec![]).unwrap();
+
+    c.donate_to_campaign(campaign_id, vec![]).unwrap();
+
+    let half = U256::from(MSG_VALUE / 2);
+    assert_eq!(c.get_donators(campaign_id), (vec![SENDER, SENDER], vec![U256::from(MSG_VALUE), half]));
+    assert_eq!(c.get_campaign(campaign_id).unwrap().5, U256::from(MSG_VALUE * 3 / 2));
+    assert_eq!(host::transfers(), vec![(SENDER, half)]);
+    assert_eq!(c.get_campaign_status(campaign_id), Ok(CampaignStatus::Successful as u8));
+    assert_eq!(
+        host::transact(|| contract().donate_to_campaign(campaign_id, vec![])),
+        Err(DeadlinePassed { deadline: U256::from(NOW), currentTime: U256::from(NOW) }.abi_encode()),
+    );
+}
+
+#[test]
+fn donations_within_the_limits_are_accepted_up_to_the_per_donor_cap() {
+    host::reset();
+    let mut c = contract();
+    let params = CampaignParams {
+        min_donation: U256::from(MSG_VALUE),
+        max_donation: U256::from(MSG_VALUE),
+        max_per_donor: U256::from(2 * MSG_VALUE),
+        ..Default::default()
+    };
+    let campaign_id = create_campaign_with(&mut c, params).unwrap();
+
+    c.donate_to_campaign(campaign_id, vec![]).unwrap();
+    c.donate_to_campaign(campaign_id, vec![]).unwrap();
+
+    assert_eq!(c.get_contribution(campaign_id, SENDER), U256::from(2 * MSG_VALUE));
+    assert_eq!(
+        host::transact(|| contract().donate_to_campaign(campaign_id, vec![])),
+        Err(DonorCapExceeded { contribution: U256::from(3 * MSG_VALUE), maxPerDonor: U256::from(2 * MSG_VALUE) }
+            .abi_encode()),
+    );
+}
+
+#[test]
+fn donations_outside_the_limits_revert() {
+    host::reset();
+    let mut c = contract();
+    let amount = U256::from(MSG_VALUE);
+    let above = U256::from(MSG_VALUE + 1);
+    let below = U256::from(MSG_VALUE - 1);
+
+    let campaign_id = create_campaign_with(&mut c, CampaignParams { min_donation: above, ..Default::default() }).unwrap();
+    assert_eq!(
+        host::transact(|| contract().donate_to_campaign(campaign_id, vec![])),
+        Err(DonationBelowMinimum { amount, minDonation: above }.abi_encode()),
+    );
+
+    let campaign_id = create_campaign_with(&mut c, CampaignParams { max_donation: below, ..Default::default() }).unwrap();
+    assert_eq!(
+        host::transact(|| contract().donate_to_campaign(campaign_id, vec![])),
+        Err(DonationAboveMaximum { amount, maxDonation: below }.abi_encode()),
+    );
+}
+
+#[test]
+fn donation_filling_the_hard_cap_may_be_below_the_minimum() {
+    host::reset();
+    let mut c = contract();
+    let params = CampaignParams {
+        target: U256::from(MSG_VALUE),
+        min_donation: U256::from(MSG_VALUE),
+        hard_cap: U256::from(MSG_VALUE * 3 / 2),
+        ..Default::default()
+    };
+    let campaign_id = create_campaign_with(&mut c, params).unwrap();
+    c.donate_to_campaign(campaign_id, vec![]).unwrap();
+
+    // Only half of this donation fits under the cap, which is below the minimum.
+    c.donate_to_campaign(campaign_id, vec![]).unwrap();
+
+    assert_eq!(c.get_contribution(campaign_id, SENDER), U256::from(MSG_VALUE * 3 / 2));
+}